@@ -1,19 +1,65 @@
 use crate::{
     email::EmailAddr,
-    pass::{session::SessionPass, time::TimePass},
-    user::{password::PasswordHash, permissions::Permissions, PassId, PhoneNumber, UserId},
+    pass::{
+        access::{AccessAttempt, AccessMethod, DenialReason},
+        calendar::{ClosureId, ClosureKind},
+        guest::GuestEntryId,
+        log::AccessLogId,
+        session::SessionPass,
+        time::TimePass,
+        window::AccessWindow,
+    },
+    token::TokenHash,
+    user::{
+        grant::PermissionGrantId,
+        password::{PasswordHash, PlainPassword},
+        permissions::Permissions,
+        reset::PasswordResetId,
+        role::RoleId,
+        two_factor::{RecoveryCodeId, TotpSecret},
+        verification::EmailVerificationId,
+        PassId, PhoneNumber, UserId,
+    },
 };
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "sqlite")]
 use {crate::table::BindValues, backend_proc_macro::BindValues};
 
 /// The type expected when creating a user.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateUser {
+    pub id: Option<UserId>,
+    pub username: String,
+    pub email: EmailAddr,
+    pub number: Option<PhoneNumber>,
+    pub password: PlainPassword,
+    pub permissions: Permissions,
+}
+
+impl CreateUser {
+    /// Hashes the password so that the user can be inserted into the database.
+    pub fn hash_password(self) -> NewUser {
+        NewUser {
+            password: self.password.hash(),
+            id: self.id,
+            username: self.username,
+            email: self.email,
+            email_verified: false,
+            number: self.number,
+            permissions: self.permissions,
+        }
+    }
+}
+
+/// A [`CreateUser`] whose password has been hashed, ready to be inserted.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "sqlite", derive(BindValues))]
-pub struct CreateUser {
+pub struct NewUser {
     pub id: Option<UserId>,
     pub username: String,
     pub email: EmailAddr,
+    pub email_verified: bool,
     pub number: Option<PhoneNumber>,
     pub password: PasswordHash,
     pub permissions: Permissions,
@@ -27,4 +73,144 @@ pub struct CreateUserPass {
     pub user_id: UserId,
     pub time_pass: TimePass,
     pub session_pass: SessionPass,
+    pub window: Option<AccessWindow>,
+}
+
+/// The type expected when adding a member to a shared pass.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreatePassMember {
+    pub pass_id: PassId,
+    pub user_id: UserId,
+}
+
+/// The type expected when logging an access attempt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreateAccessLogEntry {
+    pub id: Option<AccessLogId>,
+    pub pass_id: PassId,
+    pub user_id: UserId,
+    pub time: DateTime<Utc>,
+    pub method: Option<AccessMethod>,
+    pub denial: Option<DenialReason>,
+}
+
+impl CreateAccessLogEntry {
+    /// Records the outcome of `user_id` using the pass `pass_id` just now.
+    pub fn record(pass_id: PassId, user_id: UserId, attempt: AccessAttempt) -> Self {
+        let (method, denial) = match attempt {
+            AccessAttempt::Successful(method) => (Some(method), None),
+            AccessAttempt::Failure(reason) => (None, Some(reason)),
+        };
+        Self {
+            id: None,
+            pass_id,
+            user_id,
+            time: Utc::now(),
+            method,
+            denial,
+        }
+    }
+}
+
+/// The type expected when checking in a guest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreateGuestEntry {
+    pub id: Option<GuestEntryId>,
+    pub pass_id: PassId,
+    pub host_id: UserId,
+    pub name: String,
+    pub email: Option<EmailAddr>,
+    pub time: DateTime<Utc>,
+    pub method: AccessMethod,
+}
+
+/// The type expected when adding a closure or holiday to the calendar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreateClosure {
+    pub id: Option<ClosureId>,
+    pub date: NaiveDate,
+    pub kind: ClosureKind,
+    pub description: String,
+}
+
+/// The type expected when issuing a password reset, see
+/// [`PasswordReset::issue`](crate::user::reset::PasswordReset::issue).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreatePasswordReset {
+    pub id: Option<PasswordResetId>,
+    pub user_id: UserId,
+    pub token_hash: TokenHash,
+    pub expiry: DateTime<Utc>,
+    pub used: bool,
+}
+
+/// The type expected when issuing an email verification, see
+/// [`EmailVerification::issue`](crate::user::verification::EmailVerification::issue).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreateEmailVerification {
+    pub id: Option<EmailVerificationId>,
+    pub user_id: UserId,
+    pub email: EmailAddr,
+    pub token_hash: TokenHash,
+    pub expiry: DateTime<Utc>,
+}
+
+/// The type expected when enrolling a user in two-factor authentication, see
+/// [`TwoFactor::enrol`](crate::user::two_factor::TwoFactor::enrol).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreateTwoFactor {
+    pub user_id: UserId,
+    pub secret: TotpSecret,
+    pub enabled: bool,
+    pub last_step: Option<i64>,
+}
+
+/// The type expected when storing a recovery code, see
+/// [`RecoveryCode::generate`](crate::user::two_factor::RecoveryCode::generate).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreateRecoveryCode {
+    pub id: Option<RecoveryCodeId>,
+    pub user_id: UserId,
+    pub code_hash: TokenHash,
+    pub used: bool,
+}
+
+/// The type expected when creating a role, see [`Role::create`](crate::user::role::Role::create).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreateRole {
+    pub id: Option<RoleId>,
+    pub name: String,
+    pub permissions: Permissions,
+    pub description: String,
+}
+
+/// The type expected when assigning a role to a user, see
+/// [`UserRole::assign`](crate::user::role::UserRole::assign).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreateUserRole {
+    pub user_id: UserId,
+    pub role_id: RoleId,
+}
+
+/// The type expected when granting permissions for a limited time, see
+/// [`PermissionGrant::issue`](crate::user::grant::PermissionGrant::issue).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct CreatePermissionGrant {
+    pub id: Option<PermissionGrantId>,
+    pub user_id: UserId,
+    pub permissions: Permissions,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub granted_by: UserId,
 }
@@ -1,8 +1,26 @@
 use crate::{
     email::EmailAddr,
-    pass::{session::SessionPass, time::TimePass},
-    user::{permissions::Permissions, PassId, PhoneNumber, UserId},
+    pass::{
+        access::{AccessMethod, DenialReason},
+        calendar::{ClosureId, ClosureKind},
+        guest::GuestEntryId,
+        log::AccessLogId,
+        session::SessionPass,
+        time::TimePass,
+        window::AccessWindow,
+    },
+    token::TokenHash,
+    user::{
+        grant::PermissionGrantId,
+        permissions::Permissions,
+        reset::PasswordResetId,
+        role::RoleId,
+        two_factor::{RecoveryCodeId, TotpSecret},
+        verification::EmailVerificationId,
+        PassId, PhoneNumber, UserId,
+    },
 };
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "sqlite")]
 use {crate::table::BindValues, backend_proc_macro::BindValues};
@@ -13,6 +31,7 @@ pub struct QueryUser {
     pub id: Option<UserId>,
     pub username: Option<String>,
     pub email: Option<EmailAddr>,
+    pub email_verified: Option<bool>,
     pub number: Option<PhoneNumber>,
     pub permissions: Option<Permissions>,
 }
@@ -24,4 +43,109 @@ pub struct QueryUserPass {
     pub user_id: Option<UserId>,
     pub time_pass: Option<TimePass>,
     pub session_pass: Option<SessionPass>,
+    pub window: Option<AccessWindow>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryPassMember {
+    pub pass_id: Option<PassId>,
+    pub user_id: Option<UserId>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryAccessLogEntry {
+    pub id: Option<AccessLogId>,
+    pub pass_id: Option<PassId>,
+    pub user_id: Option<UserId>,
+    pub time: Option<DateTime<Utc>>,
+    pub method: Option<AccessMethod>,
+    pub denial: Option<DenialReason>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryGuestEntry {
+    pub id: Option<GuestEntryId>,
+    pub pass_id: Option<PassId>,
+    pub host_id: Option<UserId>,
+    pub name: Option<String>,
+    pub email: Option<EmailAddr>,
+    pub time: Option<DateTime<Utc>>,
+    pub method: Option<AccessMethod>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryClosure {
+    pub id: Option<ClosureId>,
+    pub date: Option<NaiveDate>,
+    pub kind: Option<ClosureKind>,
+    pub description: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryPasswordReset {
+    pub id: Option<PasswordResetId>,
+    pub user_id: Option<UserId>,
+    pub token_hash: Option<TokenHash>,
+    pub expiry: Option<DateTime<Utc>>,
+    pub used: Option<bool>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryEmailVerification {
+    pub id: Option<EmailVerificationId>,
+    pub user_id: Option<UserId>,
+    pub email: Option<EmailAddr>,
+    pub token_hash: Option<TokenHash>,
+    pub expiry: Option<DateTime<Utc>>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryTwoFactor {
+    pub user_id: Option<UserId>,
+    pub secret: Option<TotpSecret>,
+    pub enabled: Option<bool>,
+    pub last_step: Option<i64>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryRecoveryCode {
+    pub id: Option<RecoveryCodeId>,
+    pub user_id: Option<UserId>,
+    pub code_hash: Option<TokenHash>,
+    pub used: Option<bool>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryRole {
+    pub id: Option<RoleId>,
+    pub name: Option<String>,
+    pub permissions: Option<Permissions>,
+    pub description: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryUserRole {
+    pub user_id: Option<UserId>,
+    pub role_id: Option<RoleId>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(BindValues))]
+pub struct QueryPermissionGrant {
+    pub id: Option<PermissionGrantId>,
+    pub user_id: Option<UserId>,
+    pub permissions: Option<Permissions>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub granted_by: Option<UserId>,
 }
@@ -11,3 +11,13 @@ where
     pub match_params: A::QueryArgs,
     pub new_params: A::QueryArgs,
 }
+
+impl<A: Queryable> Update<A> {
+    /// Builds an update which sets `new_params` on the rows matching `match_params`.
+    pub fn new(match_params: A::QueryArgs, new_params: A::QueryArgs) -> Self {
+        Self {
+            match_params,
+            new_params,
+        }
+    }
+}
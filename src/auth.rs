@@ -0,0 +1,513 @@
+#![cfg(all(feature = "auth", feature = "sqlite"))]
+//! A ready-made `axum_login::AuthnBackend` and `AuthzBackend` over the SQLite store.
+
+use crate::{
+    email::EmailAddr,
+    user::{
+        grant::PermissionGrant,
+        password::{PasswordHash, PlainPassword, Verified, VerifyError},
+        permissions::Permissions,
+        role::Role,
+        two_factor::{RecoveryCode, TwoFactor, TwoFactorError, TwoFactorPolicy},
+        verification::{VerificationError, VerificationPolicy},
+        User, UserId,
+    },
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use sqlx::{Pool, Sqlite};
+use std::{collections::HashSet, fmt::Display, sync::OnceLock};
+
+/// How a user identifies themselves when logging in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Login {
+    Email(EmailAddr),
+    /// Usernames are not unique, so logging in by username fails if it is ambiguous.
+    Username(String),
+}
+
+impl From<String> for Login {
+    fn from(login: String) -> Self {
+        match login.parse() {
+            Ok(email) => Login::Email(email),
+            Err(_) => Login::Username(login),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Login {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(Login::from)
+    }
+}
+
+/// The credentials submitted by a login form.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Credentials {
+    /// An email address or username.
+    pub login: Login,
+    pub password: PlainPassword,
+    /// A TOTP or recovery code, needed if the user has enabled two-factor authentication.
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    Database(sqlx::Error),
+    /// The stored hash could not be checked, e.g. because its pepper is no longer configured.
+    Hash(VerifyError),
+    /// The password was correct but the email has to be verified first.
+    Verification(VerificationError),
+    /// The password was correct but a second factor is missing, wrong, or required and not set up.
+    TwoFactor(TwoFactorError),
+}
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::Database(e) => write!(f, "database error: {e}"),
+            AuthError::Hash(e) => e.fmt(f),
+            AuthError::Verification(e) => e.fmt(f),
+            AuthError::TwoFactor(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AuthError::Database(e) => Some(e),
+            AuthError::Hash(e) => Some(e),
+            AuthError::Verification(e) => Some(e),
+            AuthError::TwoFactor(e) => Some(e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AuthError {
+    fn from(e: sqlx::Error) -> Self {
+        AuthError::Database(e)
+    }
+}
+
+/// Authenticates [`User`]s stored in SQLite and authorizes them by their [`Permissions`].
+///
+/// Unknown logins take as long as wrong passwords, since a hash is verified either way.
+/// Outdated hashes are replaced on a successful login.
+#[derive(Debug, Clone)]
+pub struct SqliteBackend {
+    pub pool: Pool<Sqlite>,
+    pub verification: VerificationPolicy,
+    pub two_factor: TwoFactorPolicy,
+}
+
+impl SqliteBackend {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self {
+            pool,
+            verification: VerificationPolicy::default(),
+            two_factor: TwoFactorPolicy::default(),
+        }
+    }
+
+    /// The roles assigned to `user`.
+    pub async fn roles(&self, user: &User) -> Result<Vec<Role>, AuthError> {
+        Ok(sqlx::query_as(
+            "SELECT role.* FROM role JOIN userrole ON role.id = userrole.role_id
+            WHERE userrole.user_id = ?",
+        )
+        .bind(user.id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// The temporary grants given to `user`, including expired ones.
+    pub async fn grants(&self, user: &User) -> Result<Vec<PermissionGrant>, AuthError> {
+        Ok(
+            sqlx::query_as("SELECT * FROM permissiongrant WHERE user_id = ?")
+                .bind(user.id)
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    /// The permissions `user` effectively holds, see
+    /// [`User::effective_permissions_with_grants`].
+    pub async fn effective_permissions(&self, user: &User) -> Result<Permissions, AuthError> {
+        let (roles, grants) = (self.roles(user).await?, self.grants(user).await?);
+        Ok(user.effective_permissions_with_grants(&roles, &grants))
+    }
+
+    async fn find(&self, login: &Login) -> Result<Option<User>, sqlx::Error> {
+        let mut users = match login {
+            Login::Email(email) => {
+                sqlx::query_as::<_, User>("SELECT * FROM user WHERE email = ?")
+                    .bind(email)
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+            Login::Username(username) => {
+                sqlx::query_as::<_, User>("SELECT * FROM user WHERE username = ?")
+                    .bind(username)
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+        };
+        Ok(match users.len() {
+            1 => users.pop(),
+            _ => None,
+        })
+    }
+
+    /// Checks the second factor of `user`, persisting the used TOTP step or recovery code.
+    async fn check_two_factor(&self, user: &User, code: Option<&str>) -> Result<(), AuthError> {
+        let two_factor =
+            sqlx::query_as::<_, TwoFactor>("SELECT * FROM twofactor WHERE user_id = ?")
+                .bind(user.id)
+                .fetch_optional(&self.pool)
+                .await?
+                .filter(|t| t.enabled);
+        let effective = self.effective_permissions(user).await?;
+        self.two_factor
+            .check(user, effective, two_factor.as_ref())
+            .map_err(AuthError::TwoFactor)?;
+
+        let Some(mut two_factor) = two_factor else {
+            return Ok(());
+        };
+        let code = code.ok_or(AuthError::TwoFactor(TwoFactorError::InvalidCode))?;
+        match two_factor.verify(code) {
+            Ok(()) => {
+                // A concurrent login may have used the same step since it was read.
+                let recorded = sqlx::query(
+                    "UPDATE twofactor SET last_step = ?
+                    WHERE user_id = ? AND (last_step IS NULL OR last_step < ?)",
+                )
+                .bind(two_factor.last_step)
+                .bind(user.id)
+                .bind(two_factor.last_step)
+                .execute(&self.pool)
+                .await?;
+                match recorded.rows_affected() {
+                    1 => Ok(()),
+                    _ => Err(AuthError::TwoFactor(TwoFactorError::CodeReused)),
+                }
+            }
+            Err(TwoFactorError::InvalidCode) => self.redeem_recovery_code(user, code).await,
+            Err(e) => Err(AuthError::TwoFactor(e)),
+        }
+    }
+
+    async fn redeem_recovery_code(&self, user: &User, code: &str) -> Result<(), AuthError> {
+        let codes = sqlx::query_as::<_, RecoveryCode>(
+            "SELECT * FROM recoverycode WHERE user_id = ? AND used = 0",
+        )
+        .bind(user.id)
+        .fetch_all(&self.pool)
+        .await?;
+        for mut recovery in codes {
+            if recovery.redeem(code).is_ok() {
+                let redeemed =
+                    sqlx::query("UPDATE recoverycode SET used = 1 WHERE id = ? AND used = 0")
+                        .bind(recovery.id)
+                        .execute(&self.pool)
+                        .await?;
+                return match redeemed.rows_affected() {
+                    1 => Ok(()),
+                    _ => Err(AuthError::TwoFactor(TwoFactorError::AlreadyUsed)),
+                };
+            }
+        }
+        Err(AuthError::TwoFactor(TwoFactorError::InvalidCode))
+    }
+}
+
+/// A hash to verify against when the login is unknown, so that the response takes as long as for
+/// a wrong password.
+fn dummy_hash() -> &'static PasswordHash {
+    static DUMMY: OnceLock<PasswordHash> = OnceLock::new();
+    DUMMY.get_or_init(|| PlainPassword::new(crate::token::Token::generate().as_str()).hash())
+}
+
+#[async_trait]
+impl axum_login::AuthnBackend for SqliteBackend {
+    type User = User;
+    type Credentials = Credentials;
+    type Error = AuthError;
+
+    async fn authenticate(&self, creds: Credentials) -> Result<Option<User>, AuthError> {
+        let user = self.find(&creds.login).await?;
+        let hash = user.as_ref().map_or(dummy_hash(), |user| &user.password);
+        let verified = match hash.verify_async(creds.password.clone()).await {
+            Ok(verified) => verified,
+            Err(VerifyError::WrongPassword) => return Ok(None),
+            Err(e) => return Err(AuthError::Hash(e)),
+        };
+        let Some(mut user) = user else {
+            return Ok(None);
+        };
+
+        self.verification
+            .check_login(&user)
+            .map_err(AuthError::Verification)?;
+        self.check_two_factor(&user, creds.code.as_deref()).await?;
+
+        if verified == Verified::Outdated {
+            user.password = creds.password.hash_async().await;
+            sqlx::query("UPDATE user SET password = ? WHERE id = ?")
+                .bind(&user.password)
+                .bind(user.id)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(Some(user))
+    }
+
+    async fn get_user(&self, user_id: &UserId) -> Result<Option<User>, AuthError> {
+        Ok(sqlx::query_as("SELECT * FROM user WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+}
+
+/// Every single flag is an axum-login permission, so `permission_required!(backend,
+/// Permissions::USER_READ)` works. Composites like `Permissions::ADMIN` may also be checked, in
+/// which case all of their flags are required.
+#[async_trait]
+impl axum_login::AuthzBackend for SqliteBackend {
+    type Permission = Permissions;
+
+    async fn get_user_permissions(&self, user: &User) -> Result<HashSet<Permissions>, AuthError> {
+        Ok(user.permissions.flags().collect())
+    }
+
+    async fn get_group_permissions(&self, user: &User) -> Result<HashSet<Permissions>, AuthError> {
+        let roles = self.roles(user).await?;
+        Ok(roles
+            .iter()
+            .flat_map(|role| role.permissions.flags())
+            .collect())
+    }
+
+    async fn get_all_permissions(&self, user: &User) -> Result<HashSet<Permissions>, AuthError> {
+        Ok(self.effective_permissions(user).await?.flags().collect())
+    }
+
+    async fn has_perm(&self, user: &User, perm: Permissions) -> Result<bool, AuthError> {
+        Ok(self.effective_permissions(user).await?.contains(perm))
+    }
+}
+
+#[test]
+fn sqlite_backend() {
+    use crate::table::Table;
+    use axum_login::{AuthnBackend, AuthzBackend};
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        User::init(&pool).await.unwrap();
+        TwoFactor::init(&pool).await.unwrap();
+        RecoveryCode::init(&pool).await.unwrap();
+        Role::init(&pool).await.unwrap();
+        crate::user::role::UserRole::init(&pool).await.unwrap();
+        PermissionGrant::init(&pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO user (username, email, permissions, password) VALUES (?, ?, 0, ?)",
+        )
+        .bind("jo")
+        .bind("jo@example.com")
+        .bind(PlainPassword::new("hunter2").hash())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let backend = SqliteBackend::new(pool);
+        let creds = |login: &str, password: &str| Credentials {
+            login: Login::from(login.to_string()),
+            password: PlainPassword::new(password),
+            code: None,
+        };
+
+        let user = backend.authenticate(creds("jo", "hunter2")).await.unwrap();
+        let user = user.expect("correct password");
+        assert_eq!(user.email.as_ref(), "jo@example.com");
+        assert!(backend
+            .authenticate(creds("jo@example.com", "hunter2"))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(backend
+            .authenticate(creds("jo", "hunter3"))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(backend
+            .authenticate(creds("nobody", "hunter2"))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            backend.get_user(&user.id).await.unwrap(),
+            Some(user.clone())
+        );
+        assert_eq!(backend.get_user(&42).await.unwrap(), None);
+
+        // Admins have to enrol once the policy requires it, then log in with a TOTP code or a
+        // single use recovery code.
+        let strict = SqliteBackend {
+            two_factor: TwoFactorPolicy::admins(),
+            ..backend.clone()
+        };
+        sqlx::query("UPDATE user SET permissions = ? WHERE id = ?")
+            .bind(Permissions::USER_READ)
+            .bind(user.id)
+            .execute(&backend.pool)
+            .await
+            .unwrap();
+        assert!(backend
+            .authenticate(creds("jo", "hunter2"))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(matches!(
+            strict.authenticate(creds("jo", "hunter2")).await,
+            Err(AuthError::TwoFactor(TwoFactorError::Required))
+        ));
+
+        let secret = TwoFactor::enrol(user.id).secret;
+        sqlx::query("INSERT INTO twofactor (user_id, secret, enabled) VALUES (?, ?, 1)")
+            .bind(user.id)
+            .bind(&secret)
+            .execute(&backend.pool)
+            .await
+            .unwrap();
+        let with_code = |code: &str| Credentials {
+            code: Some(code.to_string()),
+            ..creds("jo", "hunter2")
+        };
+        assert!(matches!(
+            strict.authenticate(creds("jo", "hunter2")).await,
+            Err(AuthError::TwoFactor(TwoFactorError::InvalidCode))
+        ));
+        let code = secret.code_at(chrono::Utc::now());
+        assert!(strict
+            .authenticate(with_code(&code))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(matches!(
+            strict.authenticate(with_code(&code)).await,
+            Err(AuthError::TwoFactor(TwoFactorError::CodeReused))
+        ));
+
+        let (recovery, args) = RecoveryCode::generate(user.id, 1).pop().unwrap();
+        sqlx::query("INSERT INTO recoverycode (user_id, code_hash, used) VALUES (?, ?, 0)")
+            .bind(args.user_id)
+            .bind(&args.code_hash)
+            .execute(&backend.pool)
+            .await
+            .unwrap();
+        assert!(strict
+            .authenticate(with_code(recovery.as_str()))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(matches!(
+            strict.authenticate(with_code(recovery.as_str())).await,
+            Err(AuthError::TwoFactor(TwoFactorError::InvalidCode))
+        ));
+
+        // Hashes made with outdated parameters are replaced on login.
+        let outdated = "$argon2i$v=19$m=65536,t=1,p=1$c29tZXNhbHQAAAAAAAAAAA$\
+                        +r0d29hqEB0yasKr55ZgICsQGSkl0v0kgwhd+U3wyRo";
+        sqlx::query(
+            "INSERT INTO user (username, email, permissions, password) VALUES (?, ?, 0, ?)",
+        )
+        .bind("old")
+        .bind("old@example.com")
+        .bind(outdated)
+        .execute(&backend.pool)
+        .await
+        .unwrap();
+        let old = backend
+            .authenticate(creds("old", "password"))
+            .await
+            .unwrap();
+        let old = old.expect("correct password");
+        let stored = backend.get_user(&old.id).await.unwrap().unwrap();
+        assert_ne!(stored.password.as_ref() as &str, outdated);
+        assert!(!stored.password.is_outdated());
+        assert!(stored.password.verify("password").is_ok());
+
+        let user = User {
+            permissions: Permissions::USER_CRUD,
+            ..user
+        };
+        assert_eq!(backend.get_all_permissions(&user).await.unwrap().len(), 4);
+        assert!(backend
+            .has_perm(&user, Permissions::USER_READ)
+            .await
+            .unwrap());
+        assert!(backend
+            .has_perm(&user, Permissions::USER_CRUD)
+            .await
+            .unwrap());
+        assert!(!backend.has_perm(&user, Permissions::ADMIN).await.unwrap());
+
+        sqlx::query("INSERT INTO role (name, permissions, description) VALUES ('Passes', ?, '')")
+            .bind(Permissions::PASS_CRUD)
+            .execute(&backend.pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO userrole (user_id, role_id) VALUES (?, 1)")
+            .bind(user.id)
+            .execute(&backend.pool)
+            .await
+            .unwrap();
+        assert_eq!(backend.get_group_permissions(&user).await.unwrap().len(), 4);
+        assert!(backend.has_perm(&user, Role::MANAGE).await.unwrap());
+        assert!(!backend.has_perm(&user, Permissions::ADMIN).await.unwrap());
+
+        sqlx::query("INSERT INTO role (name, permissions, description) VALUES ('Sales', ?, '')")
+            .bind(Permissions::FINANCE)
+            .execute(&backend.pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO userrole (user_id, role_id) VALUES (?, 2)")
+            .bind(user.id)
+            .execute(&backend.pool)
+            .await
+            .unwrap();
+        assert!(backend.has_perm(&user, Permissions::ADMIN).await.unwrap());
+
+        // Admin permissions from a role require two-factor authentication as well.
+        assert!(strict
+            .authenticate(creds("old", "password"))
+            .await
+            .unwrap()
+            .is_some());
+        sqlx::query("INSERT INTO userrole (user_id, role_id) VALUES (?, 2)")
+            .bind(old.id)
+            .execute(&backend.pool)
+            .await
+            .unwrap();
+        assert!(matches!(
+            strict.authenticate(creds("old", "password")).await,
+            Err(AuthError::TwoFactor(TwoFactorError::Required))
+        ));
+    });
+}
@@ -0,0 +1,387 @@
+//! Decides whether a user may perform an action on a record, taking ownership into account:
+//! members may read their own account and the passes assigned to it and edit their own profile,
+//! everything else needs the matching [`Permissions`] flag.
+
+use crate::{
+    pass::UserPass,
+    user::{
+        grant::PermissionGrant,
+        permissions::{MissingPermissions, Permissions},
+        role::Role,
+        User, UserId,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Create,
+    Read,
+    Update,
+    Delete,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Action::Create => "create",
+            Action::Read => "read",
+            Action::Update => "update",
+            Action::Delete => "delete",
+        })
+    }
+}
+
+/// The record an action applies to, reduced to what authorization needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Resource {
+    User { id: UserId },
+    UserPass { owner: UserId },
+}
+
+impl Resource {
+    /// The user owning the record.
+    pub fn owner(&self) -> UserId {
+        match *self {
+            Resource::User { id } => id,
+            Resource::UserPass { owner } => owner,
+        }
+    }
+
+    /// The flag needed to perform `action` on records like this one which the actor does not own.
+    pub fn needed(&self, action: Action) -> Permissions {
+        match (self, action) {
+            (Resource::User { .. }, Action::Create) => Permissions::USER_CREATE,
+            (Resource::User { .. }, Action::Read) => Permissions::USER_READ,
+            (Resource::User { .. }, Action::Update) => Permissions::USER_UPDATE,
+            (Resource::User { .. }, Action::Delete) => Permissions::USER_DELETE,
+            (Resource::UserPass { .. }, Action::Create) => Permissions::PASS_CREATE,
+            (Resource::UserPass { .. }, Action::Read) => Permissions::PASS_READ,
+            (Resource::UserPass { .. }, Action::Update) => Permissions::PASS_UPDATE,
+            (Resource::UserPass { .. }, Action::Delete) => Permissions::PASS_DELETE,
+        }
+    }
+}
+
+impl Display for Resource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Resource::User { id } => write!(f, "user {id}"),
+            Resource::UserPass { owner } => write!(f, "pass of user {owner}"),
+        }
+    }
+}
+
+impl From<&User> for Resource {
+    fn from(user: &User) -> Self {
+        Resource::User { id: user.id }
+    }
+}
+
+impl From<&UserPass> for Resource {
+    fn from(pass: &UserPass) -> Self {
+        Resource::UserPass {
+            owner: pass.user_id,
+        }
+    }
+}
+
+/// The user performing an action, with their effective permissions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Actor {
+    pub id: UserId,
+    pub permissions: Permissions,
+    /// The part of `permissions` held only through temporary grants. These can be used, but not
+    /// handed out, see [`Actor::base`].
+    #[serde(default = "Permissions::empty")]
+    pub granted: Permissions,
+}
+
+impl Actor {
+    /// An actor holding the permissions of `user` and of `roles`, which should be the roles
+    /// assigned to the user.
+    pub fn new(user: &User, roles: &[Role]) -> Self {
+        Self {
+            id: user.id,
+            permissions: user.effective_permissions(roles),
+            granted: Permissions::NONE,
+        }
+    }
+
+    /// Like [`Actor::new`], but also holding the permissions of the user's active `grants`, see
+    /// [`User::effective_permissions_with_grants`].
+    pub fn with_grants(user: &User, roles: &[Role], grants: &[PermissionGrant]) -> Self {
+        let base = user.effective_permissions(roles);
+        let permissions = user.effective_permissions_with_grants(roles, grants);
+        Self {
+            id: user.id,
+            permissions,
+            granted: permissions.difference(base),
+        }
+    }
+
+    /// The permissions held through the user's account and roles, which are the ones they may
+    /// hand out to others.
+    pub fn base(&self) -> Permissions {
+        self.permissions.difference(self.granted)
+    }
+}
+
+/// An actor holding only the user's own permissions.
+impl From<&User> for Actor {
+    fn from(user: &User) -> Self {
+        Self::new(user, &[])
+    }
+}
+
+/// Why an action was not allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Denied {
+    pub action: Action,
+    pub resource: Resource,
+    /// The flags which would have allowed the action.
+    pub missing: MissingPermissions,
+}
+
+impl Display for Denied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "not allowed to {} {}, missing {}",
+            self.action,
+            self.resource,
+            self.missing.names().join(", ")
+        )
+    }
+}
+
+impl std::error::Error for Denied {}
+
+/// Checks whether `actor` may perform `action` on `resource`.
+///
+/// Owners may read and update their own records. Creating and deleting, as well as any action on
+/// records of other users, requires the flag given by [`Resource::needed`]. Updates which owners
+/// may not make to their own records, like topping up a pass, are checked with
+/// [`authorize_privileged`] as well.
+pub fn authorize(
+    actor: impl Into<Actor>,
+    action: Action,
+    resource: impl Into<Resource>,
+) -> Result<(), Denied> {
+    let (actor, resource) = (actor.into(), resource.into());
+    let owns = resource.owner() == actor.id && matches!(action, Action::Read | Action::Update);
+    if owns {
+        return Ok(());
+    }
+    authorize_privileged(actor, action, resource)
+}
+
+/// Checks that `actor` holds the flag given by [`Resource::needed`], whether or not they own
+/// `resource`.
+pub fn authorize_privileged(
+    actor: impl Into<Actor>,
+    action: Action,
+    resource: impl Into<Resource>,
+) -> Result<(), Denied> {
+    let (actor, resource) = (actor.into(), resource.into());
+    actor
+        .permissions
+        .require(resource.needed(action))
+        .map_err(|missing| Denied {
+            action,
+            resource,
+            missing,
+        })
+}
+
+/// A change which would let an actor gain, or hand out, more than they hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Escalation {
+    /// The actor tried to grant permissions they do not hold themselves.
+    NotHeld { missing: Permissions },
+    /// Only root may delete or demote a root account.
+    RootProtected { id: UserId },
+    /// The change would leave no root account.
+    LastRoot,
+    /// Only root may change their own permissions.
+    OwnPermissions { id: UserId },
+}
+
+impl Display for Escalation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Escalation::NotHeld { missing } => {
+                write!(f, "can not grant permissions not held: {missing}")
+            }
+            Escalation::RootProtected { id } => {
+                write!(f, "only root may delete or demote root user {id}")
+            }
+            Escalation::LastRoot => f.write_str("there has to be at least one root user"),
+            Escalation::OwnPermissions { id } => {
+                write!(f, "user {id} can not change their own permissions")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Escalation {}
+
+/// Checks that `actor` may change a user's permissions from `old` to `new`: every added bit has to
+/// be held by the actor, only root may demote root, and only root may change their own
+/// permissions. Permissions from temporary grants count for none of these, see [`Actor::base`].
+pub fn authorize_grant(
+    actor: Actor,
+    id: UserId,
+    old: Permissions,
+    new: Permissions,
+) -> Result<(), Escalation> {
+    let base = actor.base();
+    if id == actor.id && old != new && base != Permissions::ROOT {
+        return Err(Escalation::OwnPermissions { id });
+    }
+    if old == Permissions::ROOT && new != Permissions::ROOT && base != Permissions::ROOT {
+        return Err(Escalation::RootProtected { id });
+    }
+    match new.difference(old).difference(base) {
+        Permissions::NONE => Ok(()),
+        missing => Err(Escalation::NotHeld { missing }),
+    }
+}
+
+/// Checks that `actor` may delete `target`, which for root accounts only root may, without any
+/// temporary grant.
+pub fn authorize_removal(actor: Actor, target: &User) -> Result<(), Escalation> {
+    match target.permissions == Permissions::ROOT && actor.base() != Permissions::ROOT {
+        true => Err(Escalation::RootProtected { id: target.id }),
+        false => Ok(()),
+    }
+}
+
+#[test]
+fn ownership() {
+    let member = User {
+        id: 1,
+        ..Default::default()
+    };
+    let pass = UserPass {
+        id: 1,
+        user_id: 1,
+        time_pass: Default::default(),
+        session_pass: Default::default(),
+        window: None,
+    };
+    let others = UserPass { user_id: 2, ..pass };
+
+    assert_eq!(authorize(&member, Action::Read, &member), Ok(()));
+    assert_eq!(authorize(&member, Action::Update, &pass), Ok(()));
+    assert_eq!(
+        authorize(&member, Action::Delete, &member),
+        Err(Denied {
+            action: Action::Delete,
+            resource: Resource::User { id: 1 },
+            missing: MissingPermissions {
+                missing: Permissions::USER_DELETE
+            },
+        })
+    );
+    let denied = authorize(&member, Action::Read, &others).unwrap_err();
+    assert_eq!(denied.missing.missing, Permissions::PASS_READ);
+    assert_eq!(
+        denied.to_string(),
+        "not allowed to read pass of user 2, missing PASS_READ"
+    );
+
+    let front_desk = Role {
+        id: 1,
+        name: "Front desk".into(),
+        permissions: Permissions::PASS_READ,
+        description: String::new(),
+    };
+    let actor = Actor::new(&member, &[front_desk]);
+    assert_eq!(authorize(actor, Action::Read, &others), Ok(()));
+    assert!(authorize(actor, Action::Update, &others).is_err());
+}
+
+#[test]
+fn escalation() {
+    let admin = Actor {
+        id: 1,
+        permissions: Permissions::ADMIN,
+        granted: Permissions::NONE,
+    };
+    let root = Actor {
+        id: 0,
+        permissions: Permissions::ROOT,
+        granted: Permissions::NONE,
+    };
+
+    assert_eq!(
+        authorize_grant(admin, 2, Permissions::ADMIN, Permissions::ROOT),
+        Err(Escalation::NotHeld {
+            missing: Permissions::ROOT.difference(Permissions::ADMIN)
+        })
+    );
+    assert_eq!(
+        authorize_grant(admin, 1, Permissions::ADMIN, Permissions::ROOT),
+        Err(Escalation::OwnPermissions { id: 1 })
+    );
+    assert_eq!(
+        authorize_grant(admin, 1, Permissions::ADMIN, Permissions::ADMIN),
+        Ok(())
+    );
+    assert_eq!(
+        authorize_grant(admin, 2, Permissions::NONE, Permissions::USER_READ),
+        Ok(())
+    );
+    assert_eq!(
+        authorize_grant(admin, 2, Permissions::ADMIN, Permissions::NONE),
+        Ok(())
+    );
+    assert_eq!(
+        authorize_grant(admin, 0, Permissions::ROOT, Permissions::ADMIN),
+        Err(Escalation::RootProtected { id: 0 })
+    );
+    assert_eq!(
+        authorize_grant(root, 0, Permissions::ROOT, Permissions::ADMIN),
+        Ok(())
+    );
+
+    let target = User {
+        id: 0,
+        permissions: Permissions::ROOT,
+        ..Default::default()
+    };
+    assert_eq!(
+        authorize_removal(admin, &target),
+        Err(Escalation::RootProtected { id: 0 })
+    );
+    assert_eq!(authorize_removal(root, &target), Ok(()));
+
+    // Granted permissions can be used, but not handed out.
+    let granted = Actor {
+        granted: Permissions::USER_READ,
+        ..admin
+    };
+    assert_eq!(
+        granted.base(),
+        Permissions::ADMIN.difference(Permissions::USER_READ)
+    );
+    assert_eq!(
+        authorize_grant(granted, 2, Permissions::NONE, Permissions::USER_READ),
+        Err(Escalation::NotHeld {
+            missing: Permissions::USER_READ
+        })
+    );
+    let granted_root = Actor {
+        granted: Permissions::ROOT.difference(Permissions::ADMIN),
+        ..root
+    };
+    assert_eq!(
+        authorize_removal(granted_root, &target),
+        Err(Escalation::RootProtected { id: 0 })
+    );
+}
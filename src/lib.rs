@@ -1,5 +1,10 @@
 pub mod args;
+pub mod auth;
+pub mod authorize;
 pub mod email;
 pub mod pass;
+pub mod store;
 pub mod table;
+pub mod token;
 pub mod user;
+pub mod view;
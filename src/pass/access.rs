@@ -1,40 +1,146 @@
-use super::UserPass;
+use super::{calendar::ClosureKind, group::PassMember, rules::AccessRules, UserPass};
+use crate::user::User;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub enum AccessMethod {
     /// The user has a time pass which expires in the future.
     TimePass,
-    /// The user has a session pass which has been used and no grace period.
-    SessionPassSession,
+    /// The user has a session pass which has been used and no grace period. `credits` sessions were
+    /// consumed.
+    SessionPassSession { credits: u32 },
     /// The user has an active grace period for their session pass.
     SessionPassGrace,
+    /// Entry is free today and the user's valid pass was not charged.
+    FreeEntry,
 }
 
-#[derive(PartialEq, Debug)]
+/// Why an access attempt was refused.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum DenialReason {
+    /// The time pass has expired.
+    Expired,
+    /// The session pass has no sessions left and no grace period.
+    NoSessionsLeft,
+    /// The session pass has sessions left but fewer than a visit costs right now.
+    InsufficientCredits { needed: u32, available: u32 },
+    /// The pass is restricted to an [`AccessWindow`](super::window::AccessWindow) which does not
+    /// include the current time.
+    OutsideWindow,
+    /// The gym is closed today.
+    Closed,
+    /// The user is neither the owner of the pass nor a member of its group.
+    NotAMember,
+    /// The user has to verify their email before checking in.
+    EmailUnverified,
+    /// The host has already brought in as many guests as their policy allows.
+    GuestLimitReached,
+}
+
+impl DenialReason {
+    /// Picks the reason to report when both the time pass and the session pass refused entry.
+    /// An empty session pass says nothing about why the time pass failed, so the time pass reason
+    /// wins unless the session pass had sessions but not enough credits for this visit.
+    fn or_more_specific(self, session_reason: DenialReason) -> DenialReason {
+        match session_reason {
+            DenialReason::NoSessionsLeft => self,
+            other => other,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum AccessAttempt {
     Successful(AccessMethod),
-    Failure,
+    Failure(DenialReason),
 }
 
 impl AccessAttempt {
     pub fn is_success_and(&self, f: impl FnOnce(&AccessMethod) -> bool) -> bool {
         match self {
             AccessAttempt::Successful(access_method) => f(access_method),
-            AccessAttempt::Failure => false,
+            AccessAttempt::Failure(_) => false,
+        }
+    }
+}
+
+impl UserPass {
+    /// Uses the pass on behalf of `user`, who must either own the pass or be one of its
+    /// `members` and have verified their email if the rules require it.
+    pub fn use_key_as(
+        &mut self,
+        user: &User,
+        members: &[PassMember],
+        rules: &AccessRules,
+    ) -> AccessAttempt {
+        if !self.is_usable_by(user.id, members) {
+            AccessAttempt::Failure(DenialReason::NotAMember)
+        } else if rules.verification.check_check_in(user).is_err() {
+            AccessAttempt::Failure(DenialReason::EmailUnverified)
+        } else {
+            self.use_key(rules)
+        }
+    }
+
+    /// Entry on a free entry day: nothing is consumed, but the pass has to be valid, which means
+    /// an unexpired time pass or a session pass with sessions left, however much a visit would
+    /// normally cost.
+    pub(crate) fn free_entry(&self, rules: &AccessRules) -> AccessAttempt {
+        match (
+            self.time_pass.preview(rules),
+            self.session_pass.preview(rules),
+        ) {
+            (AccessAttempt::Failure(reason), AccessAttempt::Failure(_))
+                if self.session_pass.sessions_left == 0 =>
+            {
+                AccessAttempt::Failure(reason)
+            }
+            _ => AccessAttempt::Successful(AccessMethod::FreeEntry),
         }
     }
 }
 
 pub trait Pass {
-    /// Checks the key to see if it is valid and returns the status of the access attempt.
-    fn use_key(&mut self) -> AccessAttempt;
+    /// Checks the key against the gym wide `rules` and returns the status of the access attempt.
+    fn use_key(&mut self, rules: &AccessRules) -> AccessAttempt;
+
+    /// Returns the status `use_key` would return, without consuming a session or starting a grace
+    /// period.
+    fn preview(&self, rules: &AccessRules) -> AccessAttempt
+    where
+        Self: Clone,
+    {
+        self.clone().use_key(rules)
+    }
 }
 
 impl Pass for UserPass {
-    fn use_key(&mut self) -> AccessAttempt {
-        match self.time_pass.use_key() {
+    /// Refuses entry on closed days or outside of the pass's access window, otherwise tries the
+    /// time pass before falling back to the session pass.
+    ///
+    /// On free entry days nothing is consumed, see [`UserPass::free_entry`].
+    fn use_key(&mut self, rules: &AccessRules) -> AccessAttempt {
+        let now = Utc::now();
+        let closure = rules.calendar.closure_at(now);
+        if closure == Some(ClosureKind::Closed) {
+            return AccessAttempt::Failure(DenialReason::Closed);
+        }
+        if self.window.is_some_and(|window| !window.contains(now)) {
+            return AccessAttempt::Failure(DenialReason::OutsideWindow);
+        }
+        if closure == Some(ClosureKind::FreeEntry) {
+            return self.free_entry(rules);
+        }
+
+        match self.time_pass.use_key(rules) {
             AccessAttempt::Successful(access_method) => AccessAttempt::Successful(access_method),
-            AccessAttempt::Failure => self.session_pass.use_key(),
+            AccessAttempt::Failure(time_reason) => match self.session_pass.use_key(rules) {
+                AccessAttempt::Failure(session_reason) => {
+                    AccessAttempt::Failure(time_reason.or_more_specific(session_reason))
+                }
+                success => success,
+            },
         }
     }
 }
@@ -44,6 +150,8 @@ fn pass() {
     use super::{SessionPass, TimePass};
     use chrono::{Days, Utc};
 
+    let rules = AccessRules::default();
+
     let mut mem = UserPass {
         id: 0,
         user_id: 0,
@@ -51,22 +159,63 @@ fn pass() {
             expiry: Utc::now().checked_add_days(Days::new(1)).unwrap(),
         },
         session_pass: SessionPass::default(),
+        window: None,
     };
 
     mem.session_pass.sessions_left = 1;
 
-    assert!(dbg!(mem.use_key()).is_success_and(|method| method == &AccessMethod::TimePass));
+    assert!(dbg!(mem.use_key(&rules)).is_success_and(|method| method == &AccessMethod::TimePass));
     assert!(dbg!(mem.session_pass.sessions_left) == 1);
     mem.time_pass = TimePass::default();
 
     dbg!(&mem);
-    assert!(
-        dbg!(mem.use_key()).is_success_and(|method| method == &AccessMethod::SessionPassSession)
-    );
+    assert!(dbg!(mem.preview(&rules))
+        .is_success_and(|method| method == &AccessMethod::SessionPassSession { credits: 1 }));
+    assert!(dbg!(mem.session_pass.sessions_left) == 1);
+    assert!(dbg!(mem.use_key(&rules))
+        .is_success_and(|method| method == &AccessMethod::SessionPassSession { credits: 1 }));
     assert!(dbg!(mem.session_pass.sessions_left) == 0);
-    assert!(dbg!(mem.use_key()).is_success_and(|method| method == &AccessMethod::SessionPassGrace));
+    assert!(dbg!(mem.use_key(&rules))
+        .is_success_and(|method| method == &AccessMethod::SessionPassGrace));
     assert!(dbg!(mem.session_pass.sessions_left) == 0);
     mem.session_pass = SessionPass::default();
 
-    assert!(dbg!(mem.use_key()) == AccessAttempt::Failure);
+    assert!(dbg!(mem.use_key(&rules)) == AccessAttempt::Failure(DenialReason::Expired));
+
+    mem.time_pass.expiry = Utc::now().checked_add_days(Days::new(1)).unwrap();
+    mem.window = Some(super::window::AccessWindow {
+        days: super::window::Weekdays::empty(),
+        ..super::window::AccessWindow::off_peak()
+    });
+    assert!(dbg!(mem.use_key(&rules)) == AccessAttempt::Failure(DenialReason::OutsideWindow));
+
+    use super::calendar::{Calendar, Closure, ClosureKind};
+    let free_today = AccessRules {
+        credit_costs: vec![super::rules::CreditCost {
+            window: super::window::AccessWindow {
+                days: super::window::Weekdays::EVERY_DAY,
+                start: chrono::NaiveTime::MIN,
+                end: chrono::NaiveTime::MIN,
+            },
+            credits: 2,
+        }],
+        calendar: Calendar {
+            closures: vec![Closure {
+                id: 0,
+                date: Utc::now()
+                    .with_timezone(&super::gym_time_zone())
+                    .date_naive(),
+                kind: ClosureKind::FreeEntry,
+                description: String::new(),
+            }],
+        },
+        ..Default::default()
+    };
+    mem.window = None;
+    mem.time_pass = TimePass::default();
+    mem.session_pass.sessions_left = 1;
+    assert!(dbg!(mem.use_key(&free_today)) == AccessAttempt::Successful(AccessMethod::FreeEntry));
+    assert!(mem.session_pass.sessions_left == 1);
+    mem.session_pass.sessions_left = 0;
+    assert!(dbg!(mem.use_key(&free_today)) == AccessAttempt::Failure(DenialReason::Expired));
 }
@@ -0,0 +1,157 @@
+use super::time::TimePass;
+use chrono::{DateTime, Days, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::table::{BindValues, Queryable},
+    backend_proc_macro::BindValues,
+};
+
+pub type ClosureId = i64;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClosureKind {
+    /// The gym is closed and nobody may enter.
+    Closed,
+    /// The gym is open but entry does not count against anyone's pass, e.g. for a special event.
+    FreeEntry,
+}
+
+/// A day on which the gym is closed or entry is free, in the gym's local calendar.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct Closure {
+    pub id: ClosureId,
+    pub date: NaiveDate,
+    pub kind: ClosureKind,
+    pub description: String,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for Closure {
+    type CreateArgs = crate::args::create::CreateClosure;
+    type QueryArgs = crate::args::query::QueryClosure;
+}
+
+/// The closures and holidays which access evaluation should respect.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Calendar {
+    pub closures: Vec<Closure>,
+}
+
+impl Calendar {
+    /// Returns the kind of closure in effect at `time`, if any.
+    pub fn closure_at(&self, time: DateTime<Utc>) -> Option<ClosureKind> {
+        let date = time.with_timezone(&super::gym_time_zone()).date_naive();
+        self.closure_on(date)
+    }
+
+    /// Returns the kind of closure on `date`. A day which is both closed and free counts as
+    /// closed.
+    pub fn closure_on(&self, date: NaiveDate) -> Option<ClosureKind> {
+        let mut kinds = self
+            .closures
+            .iter()
+            .filter(|c| c.date == date)
+            .map(|c| c.kind);
+        kinds
+            .clone()
+            .find(|kind| *kind == ClosureKind::Closed)
+            .or_else(|| kinds.next())
+    }
+
+    /// Counts the [`ClosureKind::Closed`] days in `from..=to`.
+    pub fn closed_days_between(&self, from: NaiveDate, to: NaiveDate) -> u64 {
+        from.iter_days()
+            .take_while(|date| *date <= to)
+            .filter(|date| self.closure_on(*date) == Some(ClosureKind::Closed))
+            .count() as u64
+    }
+}
+
+impl TimePass {
+    /// Sets the expiry to `purchased_expiry`, the expiry the pass was bought with, extended by one
+    /// day for every day the gym is closed between `from` and the last day the pass covers.
+    /// Closures which fall inside the extension are compensated for as well.
+    ///
+    /// The expiry is always recomputed from `purchased_expiry`, so this can be applied again, e.g.
+    /// when closures are added, without extending the pass twice.
+    pub fn compensate_closures(
+        &mut self,
+        calendar: &Calendar,
+        from: DateTime<Utc>,
+        purchased_expiry: DateTime<Utc>,
+    ) {
+        let gym = super::gym_time_zone();
+        let from = from.with_timezone(&gym).date_naive();
+
+        let mut compensated = 0;
+        self.expiry = purchased_expiry;
+        loop {
+            let last_day = (self.expiry - Duration::nanoseconds(1))
+                .with_timezone(&gym)
+                .date_naive();
+            let closed = calendar.closed_days_between(from, last_day);
+            if closed == compensated {
+                break;
+            }
+            compensated = closed;
+            self.expiry = purchased_expiry + Days::new(compensated);
+        }
+    }
+}
+
+#[test]
+fn calendar() {
+    use chrono::TimeZone;
+
+    let date = |d| NaiveDate::from_ymd_opt(2025, 12, d).unwrap();
+    let closure = |d, kind| Closure {
+        id: d as ClosureId,
+        date: date(d),
+        kind,
+        description: String::new(),
+    };
+    let calendar = Calendar {
+        closures: vec![
+            closure(24, ClosureKind::FreeEntry),
+            closure(25, ClosureKind::Closed),
+            closure(26, ClosureKind::Closed),
+            closure(28, ClosureKind::Closed),
+        ],
+    };
+
+    assert_eq!(calendar.closure_on(date(24)), Some(ClosureKind::FreeEntry));
+    assert_eq!(calendar.closure_on(date(27)), None);
+    assert_eq!(calendar.closed_days_between(date(1), date(26)), 2);
+
+    let gym = super::gym_time_zone();
+    let mut pass = TimePass {
+        expiry: gym
+            .with_ymd_and_hms(2025, 12, 26, 12, 0, 0)
+            .unwrap()
+            .to_utc(),
+    };
+    let from = gym
+        .with_ymd_and_hms(2025, 12, 1, 12, 0, 0)
+        .unwrap()
+        .to_utc();
+    let purchased = pass.expiry;
+    pass.compensate_closures(&calendar, from, purchased);
+
+    // Two days for the 25th and 26th takes the pass up to the 28th which is closed as well.
+    let compensated = gym
+        .with_ymd_and_hms(2025, 12, 29, 12, 0, 0)
+        .unwrap()
+        .to_utc();
+    assert_eq!(pass.expiry, compensated);
+    pass.compensate_closures(&calendar, from, purchased);
+    assert_eq!(pass.expiry, compensated);
+
+    // A pass running until the end of the 24th does not cover the 25th.
+    let mut pass = TimePass::parse_at("2025-12-24", from).unwrap();
+    let purchased = pass.expiry;
+    pass.compensate_closures(&calendar, from, purchased);
+    assert_eq!(pass.expiry, purchased);
+}
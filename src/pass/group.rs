@@ -0,0 +1,89 @@
+use super::UserPass;
+use crate::user::{PassId, UserId};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::table::{BindValues, Queryable},
+    backend_proc_macro::BindValues,
+};
+
+/// Links an additional user to a [`UserPass`] so that a family or group can share its sessions or
+/// time. The owner of the pass is `UserPass::user_id` and does not need a membership.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct PassMember {
+    pub pass_id: PassId,
+    pub user_id: UserId,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for PassMember {
+    type CreateArgs = crate::args::create::CreatePassMember;
+    type QueryArgs = crate::args::query::QueryPassMember;
+}
+
+impl UserPass {
+    /// Checks whether `user_id` owns the pass or is listed in `members` for it.
+    pub fn is_usable_by(&self, user_id: UserId, members: &[PassMember]) -> bool {
+        self.user_id == user_id
+            || members
+                .iter()
+                .any(|member| member.pass_id == self.id && member.user_id == user_id)
+    }
+}
+
+#[test]
+fn shared_pass() {
+    use super::access::{AccessAttempt, DenialReason};
+    use super::log::usage_by_user;
+    use crate::args::create::CreateAccessLogEntry;
+
+    let mut pass = UserPass {
+        id: 7,
+        user_id: 1,
+        time_pass: Default::default(),
+        session_pass: "2".parse().unwrap(),
+        window: None,
+    };
+    let members = [
+        PassMember {
+            pass_id: 7,
+            user_id: 2,
+        },
+        PassMember {
+            pass_id: 8,
+            user_id: 3,
+        },
+    ];
+
+    let user = |id| crate::user::User {
+        id,
+        ..Default::default()
+    };
+    let owner = pass.use_key_as(&user(1), &members, &Default::default());
+    assert!(owner.is_success_and(|_| true));
+    pass.session_pass.last_time_used = Default::default();
+    let member = pass.use_key_as(&user(2), &members, &Default::default());
+    assert!(member.is_success_and(|_| true));
+    assert_eq!(pass.session_pass.sessions_left, 0);
+    assert_eq!(
+        pass.use_key_as(&user(3), &members, &Default::default()),
+        AccessAttempt::Failure(DenialReason::NotAMember)
+    );
+
+    let log = [(1, owner), (2, member)].map(|(user_id, attempt)| {
+        let entry = CreateAccessLogEntry::record(pass.id, user_id, attempt);
+        super::log::AccessLogEntry {
+            id: 0,
+            pass_id: entry.pass_id,
+            user_id: entry.user_id,
+            time: entry.time,
+            method: entry.method,
+            denial: entry.denial,
+        }
+    });
+    let usage = usage_by_user(&log);
+    assert_eq!(usage[&1], 1);
+    assert_eq!(usage[&2], 1);
+}
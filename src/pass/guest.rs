@@ -0,0 +1,227 @@
+use super::{
+    access::{AccessAttempt, AccessMethod, DenialReason, Pass},
+    calendar::ClosureKind,
+    rules::AccessRules,
+    UserPass,
+};
+use crate::{
+    args::create::CreateGuestEntry,
+    email::EmailAddr,
+    user::{PassId, UserId},
+};
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::table::{BindValues, Queryable},
+    backend_proc_macro::BindValues,
+};
+
+pub type GuestEntryId = i64;
+
+/// A friend brought along by a member, paid for from the host's pass.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct GuestEntry {
+    pub id: GuestEntryId,
+    pub pass_id: PassId,
+    pub host_id: UserId,
+    pub name: String,
+    pub email: Option<EmailAddr>,
+    pub time: DateTime<Utc>,
+    /// Either [`AccessMethod::TimePass`] when the time pass guest allowance was used,
+    /// [`AccessMethod::SessionPassSession`] when sessions were consumed or
+    /// [`AccessMethod::FreeEntry`] on free entry days.
+    pub method: AccessMethod,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for GuestEntry {
+    type CreateArgs = crate::args::create::CreateGuestEntry;
+    type QueryArgs = crate::args::query::QueryGuestEntry;
+}
+
+/// Limits on how many guests may be brought in on a single pass.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GuestPolicy {
+    pub per_day: u32,
+    pub per_month: u32,
+    /// Guests per month which are covered by a valid time pass without consuming a session.
+    pub time_pass_monthly_allowance: u32,
+}
+
+impl Default for GuestPolicy {
+    /// One guest a day, four a month and one free guest a month for time pass holders.
+    fn default() -> Self {
+        Self {
+            per_day: 1,
+            per_month: 4,
+            time_pass_monthly_allowance: 1,
+        }
+    }
+}
+
+impl UserPass {
+    /// Checks in the guest `name`, optionally reachable at `email`, on this pass and returns the
+    /// entry to record. `history` must contain the previous guest entries of the pass for at least
+    /// the current month.
+    ///
+    /// The guest is covered by the time pass allowance if there is one left this month, otherwise
+    /// a visit is charged to the session pass. Unlike the host, a guest never gets a grace period.
+    pub fn check_in_guest(
+        &mut self,
+        name: impl Into<String>,
+        email: Option<EmailAddr>,
+        rules: &AccessRules,
+        history: &[GuestEntry],
+    ) -> Result<CreateGuestEntry, DenialReason> {
+        let policy = &rules.guests;
+        let gym = super::gym_time_zone();
+        let now = Utc::now().with_timezone(&gym);
+        let closure = rules.calendar.closure_at(now.to_utc());
+        if closure == Some(ClosureKind::Closed) {
+            return Err(DenialReason::Closed);
+        }
+        if self
+            .window
+            .is_some_and(|window| !window.contains(now.to_utc()))
+        {
+            return Err(DenialReason::OutsideWindow);
+        }
+
+        let this_month = history.iter().filter(|entry| {
+            let time = entry.time.with_timezone(&gym);
+            entry.pass_id == self.id && time.year() == now.year() && time.month() == now.month()
+        });
+        let today = this_month
+            .clone()
+            .filter(|entry| entry.time.with_timezone(&gym).day() == now.day())
+            .count();
+        let allowance_used = this_month
+            .clone()
+            .filter(|entry| entry.method == AccessMethod::TimePass)
+            .count();
+
+        if today >= policy.per_day as usize || this_month.count() >= policy.per_month as usize {
+            return Err(DenialReason::GuestLimitReached);
+        }
+
+        let credits = rules.credits_at(now.to_utc());
+        let time_pass_valid = self.time_pass.preview(rules).is_success_and(|_| true);
+        let attempt = if closure == Some(ClosureKind::FreeEntry) {
+            self.free_entry(rules)
+        } else if time_pass_valid && allowance_used < policy.time_pass_monthly_allowance as usize {
+            AccessAttempt::Successful(AccessMethod::TimePass)
+        } else if time_pass_valid && self.session_pass.sessions_left == 0 {
+            AccessAttempt::Failure(DenialReason::InsufficientCredits {
+                needed: credits,
+                available: 0,
+            })
+        } else {
+            self.session_pass.consume(credits)
+        };
+
+        match attempt {
+            AccessAttempt::Successful(method) => Ok(CreateGuestEntry {
+                id: None,
+                pass_id: self.id,
+                host_id: self.user_id,
+                name: name.into(),
+                email,
+                time: now.to_utc(),
+                method,
+            }),
+            AccessAttempt::Failure(reason) => Err(reason),
+        }
+    }
+}
+
+#[test]
+fn guest_check_in() {
+    use chrono::Days;
+
+    let mut pass = UserPass {
+        id: 1,
+        user_id: 1,
+        time_pass: super::time::TimePass {
+            expiry: Utc::now().checked_add_days(Days::new(1)).unwrap(),
+        },
+        session_pass: "1".parse().unwrap(),
+        window: None,
+    };
+    let rules = AccessRules {
+        guests: GuestPolicy {
+            per_day: 3,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let email: EmailAddr = "guest@example.com".parse().unwrap();
+    let mut history = Vec::new();
+    let check_in = |pass: &mut UserPass, history: &mut Vec<GuestEntry>| {
+        let entry = pass.check_in_guest("Guest", Some(email.clone()), &rules, history)?;
+        assert_eq!(entry.name, "Guest");
+        assert_eq!(entry.email.as_ref(), Some(&email));
+        assert_eq!((entry.pass_id, entry.host_id), (pass.id, pass.user_id));
+        history.push(GuestEntry {
+            id: history.len() as GuestEntryId,
+            pass_id: entry.pass_id,
+            host_id: entry.host_id,
+            name: entry.name,
+            email: entry.email,
+            time: entry.time,
+            method: entry.method,
+        });
+        Ok(entry.method)
+    };
+
+    assert_eq!(
+        check_in(&mut pass, &mut history),
+        Ok(AccessMethod::TimePass)
+    );
+    assert_eq!(pass.session_pass.sessions_left, 1);
+    assert_eq!(
+        check_in(&mut pass, &mut history),
+        Ok(AccessMethod::SessionPassSession { credits: 1 })
+    );
+    assert_eq!(pass.session_pass.sessions_left, 0);
+    assert!(matches!(
+        check_in(&mut pass, &mut history),
+        Err(DenialReason::InsufficientCredits { available: 0, .. })
+    ));
+
+    // Free entry days still need a valid host pass.
+    let free_today = AccessRules {
+        calendar: super::calendar::Calendar {
+            closures: vec![super::calendar::Closure {
+                id: 0,
+                date: Utc::now()
+                    .with_timezone(&super::gym_time_zone())
+                    .date_naive(),
+                kind: ClosureKind::FreeEntry,
+                description: String::new(),
+            }],
+        },
+        ..Default::default()
+    };
+    let mut expired = UserPass {
+        id: 2,
+        time_pass: Default::default(),
+        ..pass
+    };
+    assert_eq!(
+        expired
+            .check_in_guest("Guest", None, &free_today, &[])
+            .map(|entry| entry.method),
+        Err(DenialReason::Expired)
+    );
+    expired.session_pass.sessions_left = 1;
+    assert_eq!(
+        expired
+            .check_in_guest("Guest", None, &free_today, &[])
+            .map(|entry| entry.method),
+        Ok(AccessMethod::FreeEntry)
+    );
+    assert_eq!(expired.session_pass.sessions_left, 1);
+}
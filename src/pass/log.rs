@@ -0,0 +1,55 @@
+use super::access::{AccessAttempt, AccessMethod, DenialReason};
+use crate::user::{PassId, UserId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::table::{BindValues, Queryable},
+    backend_proc_macro::BindValues,
+};
+
+pub type AccessLogId = i64;
+
+/// A record of a single attempt to use a pass. For shared passes `user_id` is the member who
+/// checked in, not the owner of the pass.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct AccessLogEntry {
+    pub id: AccessLogId,
+    pub pass_id: PassId,
+    pub user_id: UserId,
+    pub time: DateTime<Utc>,
+    /// Set when access was granted.
+    pub method: Option<AccessMethod>,
+    /// Set when access was refused.
+    pub denial: Option<DenialReason>,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for AccessLogEntry {
+    type CreateArgs = crate::args::create::CreateAccessLogEntry;
+    type QueryArgs = crate::args::query::QueryAccessLogEntry;
+}
+
+impl AccessLogEntry {
+    pub fn attempt(&self) -> Option<AccessAttempt> {
+        match (self.method, self.denial) {
+            (Some(method), None) => Some(AccessAttempt::Successful(method)),
+            (None, Some(reason)) => Some(AccessAttempt::Failure(reason)),
+            _ => None,
+        }
+    }
+}
+
+/// Counts the successful check-ins of each user in `entries`.
+pub fn usage_by_user<'a>(
+    entries: impl IntoIterator<Item = &'a AccessLogEntry>,
+) -> HashMap<UserId, u32> {
+    let mut usage = HashMap::new();
+    for entry in entries.into_iter().filter(|entry| entry.method.is_some()) {
+        *usage.entry(entry.user_id).or_default() += 1;
+    }
+    usage
+}
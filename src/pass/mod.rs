@@ -1,11 +1,19 @@
 pub mod access;
+pub mod calendar;
+pub mod group;
+pub mod guest;
+pub mod log;
+pub mod rules;
 pub mod session;
 pub mod sqlx_impl;
 pub mod time;
+pub mod window;
 
+use chrono::FixedOffset;
 use serde::{Deserialize, Serialize};
 use session::SessionPass;
 use time::TimePass;
+use window::AccessWindow;
 
 use crate::user::{PassId, UserId};
 
@@ -22,6 +30,8 @@ pub struct UserPass {
     pub user_id: UserId,
     pub time_pass: TimePass,
     pub session_pass: SessionPass,
+    /// Restricts when the pass may be used, e.g. for off-peak memberships.
+    pub window: Option<AccessWindow>,
 }
 
 #[cfg(feature = "sqlite")]
@@ -29,3 +39,11 @@ impl Queryable for UserPass {
     type CreateArgs = crate::args::create::CreateUserPass;
     type QueryArgs = crate::args::query::QueryUserPass;
 }
+
+/// The offset of the gym's local time from UTC.
+pub const GYM_UTC_OFFSET: i32 = 2 * 3600;
+
+/// The time zone in which opening hours, access windows and session grace periods are evaluated.
+pub fn gym_time_zone() -> FixedOffset {
+    FixedOffset::east_opt(GYM_UTC_OFFSET).unwrap()
+}
@@ -0,0 +1,62 @@
+use super::{calendar::Calendar, guest::GuestPolicy, window::AccessWindow};
+use crate::user::verification::VerificationPolicy;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Charges `credits` sessions for a visit which starts inside `window`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CreditCost {
+    pub window: AccessWindow,
+    pub credits: u32,
+}
+
+/// Gym wide rules which apply to every pass when it is used.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AccessRules {
+    /// Visits which do not start inside any of these windows cost a single credit. If windows
+    /// overlap the first matching one is charged.
+    pub credit_costs: Vec<CreditCost>,
+    pub guests: GuestPolicy,
+    pub calendar: Calendar,
+    pub verification: VerificationPolicy,
+}
+
+impl AccessRules {
+    /// The number of sessions a visit starting at `time` consumes from a session pass.
+    pub fn credits_at(&self, time: DateTime<Utc>) -> u32 {
+        self.credit_costs
+            .iter()
+            .find(|cost| cost.window.contains(time))
+            .map_or(1, |cost| cost.credits)
+    }
+}
+
+#[test]
+fn credit_costs() {
+    use super::window::Weekdays;
+    use chrono::{NaiveTime, TimeZone};
+
+    let rules = AccessRules {
+        credit_costs: vec![CreditCost {
+            window: AccessWindow {
+                days: Weekdays::WEEKEND,
+                start: NaiveTime::MIN,
+                end: NaiveTime::MIN,
+            },
+            credits: 2,
+        }],
+        ..Default::default()
+    };
+    let at = |d| {
+        super::gym_time_zone()
+            .with_ymd_and_hms(2025, 3, d, 12, 0, 0)
+            .unwrap()
+            .to_utc()
+    };
+
+    // 2025-03-07 is a Friday and 2025-03-08 a Saturday.
+    assert_eq!(rules.credits_at(at(7)), 1);
+    assert_eq!(rules.credits_at(at(8)), 2);
+    assert_eq!(rules.credits_at(at(9)), 2);
+    assert_eq!(AccessRules::default().credits_at(at(8)), 1);
+}
@@ -1,5 +1,8 @@
-use super::access::{AccessAttempt, AccessMethod, Pass};
-use chrono::{DateTime, Duration, FixedOffset, Timelike, Utc};
+use super::{
+    access::{AccessAttempt, AccessMethod, DenialReason, Pass},
+    rules::AccessRules,
+};
+use chrono::{DateTime, Duration, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
@@ -54,12 +57,10 @@ impl Pass for SessionPass {
     /// | 20:00 - 23:59 | 05:00 __next__ day |
     ///
     /// ```
-    fn use_key(&mut self) -> AccessAttempt {
+    /// Consuming a session costs as many credits as `rules` charge for the current time.
+    fn use_key(&mut self, rules: &AccessRules) -> AccessAttempt {
         let one_day = 86400;
-        let hour = 3600;
-        let local_time = self
-            .last_time_used
-            .with_timezone(&FixedOffset::east_opt(2 * hour).unwrap());
+        let local_time = self.last_time_used.with_timezone(&super::gym_time_zone());
         let expiry = {
             let next_midnight = local_time
                 + Duration::seconds(one_day - local_time.num_seconds_from_midnight() as i64);
@@ -70,16 +71,33 @@ impl Pass for SessionPass {
             }
         };
 
-        let now = Utc::now().with_timezone(&FixedOffset::east_opt(2 * hour).unwrap());
+        let now = Utc::now().with_timezone(&super::gym_time_zone());
 
         if now < expiry {
             AccessAttempt::Successful(AccessMethod::SessionPassGrace)
-        } else if self.sessions_left > 0 {
-            self.sessions_left -= 1;
-            self.last_time_used = now.to_utc();
-            AccessAttempt::Successful(AccessMethod::SessionPassSession)
         } else {
-            AccessAttempt::Failure
+            let attempt = self.consume(rules.credits_at(now.to_utc()));
+            if attempt.is_success_and(|_| true) {
+                self.last_time_used = now.to_utc();
+            }
+            attempt
+        }
+    }
+}
+
+impl SessionPass {
+    /// Takes `credits` sessions off the pass without touching the grace period.
+    pub(crate) fn consume(&mut self, credits: u32) -> AccessAttempt {
+        if self.sessions_left == 0 {
+            AccessAttempt::Failure(DenialReason::NoSessionsLeft)
+        } else if self.sessions_left < credits {
+            AccessAttempt::Failure(DenialReason::InsufficientCredits {
+                needed: credits,
+                available: self.sessions_left,
+            })
+        } else {
+            self.sessions_left -= credits;
+            AccessAttempt::Successful(AccessMethod::SessionPassSession { credits })
         }
     }
 }
@@ -87,18 +105,20 @@ impl Pass for SessionPass {
 #[test]
 fn session_pass() {
     use chrono::{Duration, Local};
+
+    let rules = AccessRules::default();
     let mut pass = SessionPass {
         sessions_left: 3,
         ..Default::default()
     };
 
     assert!(pass
-        .use_key()
-        .is_success_and(|method| method == &AccessMethod::SessionPassSession));
+        .use_key(&rules)
+        .is_success_and(|method| method == &AccessMethod::SessionPassSession { credits: 1 }));
     assert!(pass.sessions_left == 2);
 
     assert!(pass
-        .use_key()
+        .use_key(&rules)
         .is_success_and(|method| method == &AccessMethod::SessionPassGrace));
     assert!(pass.sessions_left == 2);
 
@@ -107,10 +127,36 @@ fn session_pass() {
     let mut pass = SessionPass::default();
     let inital_last_use = pass.last_time_used;
 
-    assert!(pass.use_key() == AccessAttempt::Failure);
+    assert!(pass.use_key(&rules) == AccessAttempt::Failure(DenialReason::NoSessionsLeft));
     assert!(pass.sessions_left == 0);
-    assert!(pass.use_key() == AccessAttempt::Failure);
+    assert!(pass.use_key(&rules) == AccessAttempt::Failure(DenialReason::NoSessionsLeft));
     assert!(pass.sessions_left == 0);
 
     assert!(pass.last_time_used == inital_last_use);
+
+    let two_credits_all_week = AccessRules {
+        credit_costs: vec![super::rules::CreditCost {
+            window: super::window::AccessWindow {
+                days: super::window::Weekdays::EVERY_DAY,
+                start: chrono::NaiveTime::MIN,
+                end: chrono::NaiveTime::MIN,
+            },
+            credits: 2,
+        }],
+        ..Default::default()
+    };
+    let mut pass = SessionPass {
+        sessions_left: 3,
+        ..Default::default()
+    };
+    assert!(pass.preview(&two_credits_all_week) == pass.use_key(&two_credits_all_week));
+    assert!(pass.sessions_left == 1);
+    pass.last_time_used = DateTime::<Utc>::UNIX_EPOCH;
+    assert!(
+        pass.use_key(&two_credits_all_week)
+            == AccessAttempt::Failure(DenialReason::InsufficientCredits {
+                needed: 2,
+                available: 1
+            })
+    );
 }
@@ -2,7 +2,15 @@
 
 use sqlx::{Decode, Encode, Sqlite, Type};
 
-use super::{session::SessionPass, time::TimePass};
+use super::{
+    access::{AccessMethod, DenialReason},
+    calendar::ClosureKind,
+    session::SessionPass,
+    time::TimePass,
+    window::{AccessWindow, Weekdays},
+};
+use chrono::{NaiveTime, Timelike};
+
 impl Type<Sqlite> for TimePass {
     fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
         <[u8] as Type<Sqlite>>::type_info()
@@ -58,3 +66,162 @@ impl<'q> Decode<'q, Sqlite> for SessionPass {
         Ok(unsafe { *tp_pointer })
     }
 }
+
+impl Type<Sqlite> for AccessWindow {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <[u8] as Type<Sqlite>>::type_info()
+    }
+}
+
+/// Stored as the day bits followed by the start and end as little endian seconds from midnight.
+impl<'q> Encode<'q, Sqlite> for AccessWindow {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        let mut raw = [0u8; 9];
+        raw[0] = self.days.bits();
+        raw[1..5].copy_from_slice(&self.start.num_seconds_from_midnight().to_le_bytes());
+        raw[5..9].copy_from_slice(&self.end.num_seconds_from_midnight().to_le_bytes());
+        Encode::<Sqlite>::encode(raw.to_vec(), buf)
+    }
+}
+
+impl<'q> Decode<'q, Sqlite> for AccessWindow {
+    fn decode(
+        value: <Sqlite as sqlx::Database>::ValueRef<'q>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let bytes: &[u8] = Decode::<Sqlite>::decode(value)?;
+        let raw: &[u8; 9] = bytes.try_into()?;
+        let time = |secs: &[u8]| {
+            let secs = u32::from_le_bytes(secs.try_into().unwrap());
+            NaiveTime::from_num_seconds_from_midnight_opt(secs, 0)
+                .ok_or_else(|| format!("{secs} is not a valid number of seconds from midnight"))
+        };
+        Ok(Self {
+            days: Weekdays::from_bits_truncate(raw[0]),
+            start: time(&raw[1..5])?,
+            end: time(&raw[5..9])?,
+        })
+    }
+}
+
+impl Type<Sqlite> for AccessMethod {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <str as Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for AccessMethod {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        let name = match self {
+            AccessMethod::TimePass => "time_pass".to_string(),
+            AccessMethod::SessionPassSession { credits } => {
+                format!("session_pass_session:{credits}")
+            }
+            AccessMethod::SessionPassGrace => "session_pass_grace".to_string(),
+            AccessMethod::FreeEntry => "free_entry".to_string(),
+        };
+        Encode::<Sqlite>::encode(name, buf)
+    }
+}
+
+impl<'q> Decode<'q, Sqlite> for AccessMethod {
+    fn decode(
+        value: <Sqlite as sqlx::Database>::ValueRef<'q>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let name = <&str as Decode<Sqlite>>::decode(value)?;
+        match name.split_once(':') {
+            None if name == "time_pass" => Ok(AccessMethod::TimePass),
+            None if name == "session_pass_grace" => Ok(AccessMethod::SessionPassGrace),
+            None if name == "free_entry" => Ok(AccessMethod::FreeEntry),
+            Some(("session_pass_session", credits)) => Ok(AccessMethod::SessionPassSession {
+                credits: credits.parse()?,
+            }),
+            _ => Err(format!("unknown access method {name:?}").into()),
+        }
+    }
+}
+
+impl Type<Sqlite> for DenialReason {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <str as Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for DenialReason {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        let name = match self {
+            DenialReason::Expired => "expired".to_string(),
+            DenialReason::NoSessionsLeft => "no_sessions_left".to_string(),
+            DenialReason::InsufficientCredits { needed, available } => {
+                format!("insufficient_credits:{needed}:{available}")
+            }
+            DenialReason::OutsideWindow => "outside_window".to_string(),
+            DenialReason::Closed => "closed".to_string(),
+            DenialReason::NotAMember => "not_a_member".to_string(),
+            DenialReason::EmailUnverified => "email_unverified".to_string(),
+            DenialReason::GuestLimitReached => "guest_limit_reached".to_string(),
+        };
+        Encode::<Sqlite>::encode(name, buf)
+    }
+}
+
+impl<'q> Decode<'q, Sqlite> for DenialReason {
+    fn decode(
+        value: <Sqlite as sqlx::Database>::ValueRef<'q>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let name = <&str as Decode<Sqlite>>::decode(value)?;
+        match name.split(':').collect::<Vec<_>>().as_slice() {
+            ["expired"] => Ok(DenialReason::Expired),
+            ["no_sessions_left"] => Ok(DenialReason::NoSessionsLeft),
+            ["insufficient_credits", needed, available] => Ok(DenialReason::InsufficientCredits {
+                needed: needed.parse()?,
+                available: available.parse()?,
+            }),
+            ["outside_window"] => Ok(DenialReason::OutsideWindow),
+            ["closed"] => Ok(DenialReason::Closed),
+            ["not_a_member"] => Ok(DenialReason::NotAMember),
+            ["email_unverified"] => Ok(DenialReason::EmailUnverified),
+            ["guest_limit_reached"] => Ok(DenialReason::GuestLimitReached),
+            _ => Err(format!("unknown denial reason {name:?}").into()),
+        }
+    }
+}
+
+impl Type<Sqlite> for ClosureKind {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <str as Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for ClosureKind {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        let name = match self {
+            ClosureKind::Closed => "closed",
+            ClosureKind::FreeEntry => "free_entry",
+        };
+        Encode::<Sqlite>::encode(name, buf)
+    }
+}
+
+impl<'q> Decode<'q, Sqlite> for ClosureKind {
+    fn decode(
+        value: <Sqlite as sqlx::Database>::ValueRef<'q>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        match <&str as Decode<Sqlite>>::decode(value)? {
+            "closed" => Ok(ClosureKind::Closed),
+            "free_entry" => Ok(ClosureKind::FreeEntry),
+            other => Err(format!("unknown closure kind {other:?}").into()),
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Days, Months, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 
-use super::access::{AccessAttempt, AccessMethod, Pass};
+use super::{
+    access::{AccessAttempt, AccessMethod, DenialReason, Pass},
+    rules::AccessRules,
+};
 
 #[repr(C)]
 #[derive(Serialize, PartialEq, Deserialize, Debug, Clone, Copy)]
@@ -10,14 +13,127 @@ pub struct TimePass {
     pub expiry: DateTime<Utc>,
 }
 
+/// The formats accepted when parsing a [`TimePass`], in the order they are tried.
+pub const ACCEPTED_FORMATS: &[&str] = &[
+    "RFC 3339, e.g. 2025-03-01T18:00:00Z or 2025-03-01T20:00:00+02:00",
+    "local date and time, e.g. 2025-03-01 20:00:00 or 2025-03-01 20:00",
+    "local date, valid until the end of that day, e.g. 2025-03-01",
+    "relative to now, e.g. 30d, +2w, 3 months or 1y",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimePassParseError {
+    /// The input did not match any of the [`ACCEPTED_FORMATS`].
+    UnknownFormat(String),
+    /// The input was understood but the resulting expiry can not be represented.
+    OutOfRange(String),
+}
+
+impl TimePassParseError {
+    pub fn accepted_formats(&self) -> &'static [&'static str] {
+        ACCEPTED_FORMATS
+    }
+}
+
+impl Display for TimePassParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimePassParseError::UnknownFormat(input) => {
+                write!(
+                    f,
+                    "{input:?} is not a valid pass expiry. Accepted formats are:"
+                )?;
+                for format in ACCEPTED_FORMATS {
+                    write!(f, "\n  - {format}")?;
+                }
+                Ok(())
+            }
+            TimePassParseError::OutOfRange(input) => {
+                write!(f, "{input:?} is too far in the future for a pass expiry")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TimePassParseError {}
+
+impl TimePass {
+    /// Parses an expiry date for the pass, resolving relative durations from `now`. See
+    /// [`ACCEPTED_FORMATS`] for the formats which are understood. Times without an offset are in
+    /// the gym's time zone.
+    pub fn parse_at(s: &str, now: DateTime<Utc>) -> Result<Self, TimePassParseError> {
+        let s = s.trim();
+        let gym = super::gym_time_zone();
+        let local = |naive: NaiveDateTime| gym.from_local_datetime(&naive).single();
+
+        let expiry = if let Ok(expiry) = DateTime::parse_from_rfc3339(s) {
+            Some(expiry.to_utc())
+        } else if let Ok(expiry) = DateTime::<Utc>::from_str(s) {
+            Some(expiry)
+        } else if let Some(naive) = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        {
+            local(naive).map(|expiry| expiry.to_utc())
+        } else if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            let end_of_day = date
+                .checked_add_days(Days::new(1))
+                .ok_or_else(|| TimePassParseError::OutOfRange(s.into()))?;
+            local(end_of_day.and_time(chrono::NaiveTime::MIN)).map(|expiry| expiry.to_utc())
+        } else {
+            let (amount, unit) =
+                parse_relative(s).ok_or_else(|| TimePassParseError::UnknownFormat(s.into()))?;
+            let now = now.with_timezone(&gym);
+            let expiry = match unit {
+                RelativeUnit::Days => now.checked_add_days(Days::new(amount)),
+                RelativeUnit::Weeks => amount
+                    .checked_mul(7)
+                    .and_then(|days| now.checked_add_days(Days::new(days))),
+                RelativeUnit::Months => u32::try_from(amount)
+                    .ok()
+                    .and_then(|months| now.checked_add_months(Months::new(months))),
+                RelativeUnit::Years => u32::try_from(amount)
+                    .ok()
+                    .and_then(|years| years.checked_mul(12))
+                    .and_then(|months| now.checked_add_months(Months::new(months))),
+            };
+            expiry.map(|expiry| expiry.to_utc())
+        };
+
+        expiry
+            .map(|expiry| TimePass { expiry })
+            .ok_or_else(|| TimePassParseError::OutOfRange(s.into()))
+    }
+}
+
+enum RelativeUnit {
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+/// Parses durations such as `30d`, `+2 weeks` or `1y`. Note that `m` means months.
+fn parse_relative(s: &str) -> Option<(u64, RelativeUnit)> {
+    let s = s.strip_prefix('+').unwrap_or(s);
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = s.split_at(split);
+    let unit = match unit.trim_start().to_ascii_lowercase().as_str() {
+        "d" | "day" | "days" => RelativeUnit::Days,
+        "w" | "week" | "weeks" => RelativeUnit::Weeks,
+        "m" | "month" | "months" => RelativeUnit::Months,
+        "y" | "year" | "years" => RelativeUnit::Years,
+        _ => return None,
+    };
+    Some((amount.parse().ok()?, unit))
+}
+
 impl FromStr for TimePass {
-    type Err = chrono::ParseError;
-    /// Parses a time into an expiry date for the pass. The format is %Y-%m-%d %H:%M:%S and must be
-    /// in UTC format!!
+    type Err = TimePassParseError;
+    /// Parses an expiry date for the pass, see [`TimePass::parse_at`]. Relative durations are
+    /// resolved from the current time.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(TimePass {
-            expiry: DateTime::<Utc>::from_str(s)?,
-        })
+        Self::parse_at(s, Utc::now())
     }
 }
 
@@ -31,11 +147,53 @@ impl Default for TimePass {
 }
 
 impl Pass for TimePass {
-    fn use_key(&mut self) -> AccessAttempt {
+    /// A time pass is valid until its expiry, the access rules do not change that.
+    fn use_key(&mut self, _rules: &AccessRules) -> AccessAttempt {
         if Utc::now() < self.expiry {
             AccessAttempt::Successful(AccessMethod::TimePass)
         } else {
-            AccessAttempt::Failure
+            AccessAttempt::Failure(DenialReason::Expired)
         }
     }
 }
+
+#[test]
+fn time_pass_parse() {
+    use chrono::Datelike;
+
+    let gym = super::gym_time_zone();
+    let now = gym
+        .with_ymd_and_hms(2025, 1, 31, 10, 0, 0)
+        .unwrap()
+        .to_utc();
+    let parse = |s| TimePass::parse_at(s, now).map(|pass| pass.expiry);
+    let local = |m, d, h, min| {
+        Ok(gym
+            .with_ymd_and_hms(2025, m, d, h, min, 0)
+            .unwrap()
+            .to_utc())
+    };
+
+    assert_eq!(parse("2025-03-01T18:00:00Z"), local(3, 1, 20, 0));
+    assert_eq!(parse("2025-03-01T20:00:00+02:00"), local(3, 1, 20, 0));
+    assert_eq!(parse("2025-03-01 18:00:00Z"), local(3, 1, 20, 0));
+    assert_eq!(parse("2025-03-01 20:00:00"), local(3, 1, 20, 0));
+    assert_eq!(parse("2025-03-01 20:00"), local(3, 1, 20, 0));
+    assert_eq!(parse(" 2025-03-01 "), local(3, 2, 0, 0));
+    assert_eq!(parse("30d"), local(3, 2, 10, 0));
+    assert_eq!(parse("+2w"), local(2, 14, 10, 0));
+    assert_eq!(parse("1 month"), local(2, 28, 10, 0));
+    assert_eq!(parse("3 Months"), local(4, 30, 10, 0));
+    assert!(parse("1y").is_ok_and(|expiry| expiry.with_timezone(&gym).year() == 2026));
+
+    let err = parse("next tuesday").unwrap_err();
+    assert_eq!(
+        err,
+        TimePassParseError::UnknownFormat("next tuesday".into())
+    );
+    assert!(err.to_string().contains("30d"));
+    assert!(matches!(
+        parse("99999999999y"),
+        Err(TimePassParseError::OutOfRange(_))
+    ));
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+bitflags::bitflags! {
+    /// The days of the week on which a pass may be used.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Weekdays: u8 {
+        const MONDAY    = 1 << 0;
+        const TUESDAY   = 1 << 1;
+        const WEDNESDAY = 1 << 2;
+        const THURSDAY  = 1 << 3;
+        const FRIDAY    = 1 << 4;
+        const SATURDAY  = 1 << 5;
+        const SUNDAY    = 1 << 6;
+
+        const WORKDAYS =
+              Self::MONDAY.bits()
+            | Self::TUESDAY.bits()
+            | Self::WEDNESDAY.bits()
+            | Self::THURSDAY.bits()
+            | Self::FRIDAY.bits();
+
+        const WEEKEND = Self::SATURDAY.bits() | Self::SUNDAY.bits();
+
+        const EVERY_DAY = Self::WORKDAYS.bits() | Self::WEEKEND.bits();
+    }
+}
+
+impl From<Weekday> for Weekdays {
+    fn from(day: Weekday) -> Self {
+        Self::from_bits_truncate(1 << day.num_days_from_monday())
+    }
+}
+
+impl Serialize for Weekdays {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        <u8 as Serialize>::serialize(&self.bits(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Weekdays {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        <u8 as Deserialize>::deserialize(deserializer).map(Weekdays::from_bits_truncate)
+    }
+}
+
+/// A recurring window in the gym's local time during which a pass may be used.
+///
+/// The window spans `start..end` on each of `days`. A window whose `end` is not after its `start`
+/// runs past midnight and is attributed to the day it started on.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessWindow {
+    pub days: Weekdays,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl AccessWindow {
+    /// Off-peak hours: weekdays before 16:00.
+    pub fn off_peak() -> Self {
+        Self {
+            days: Weekdays::WORKDAYS,
+            start: NaiveTime::MIN,
+            end: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+        }
+    }
+
+    /// Checks whether `time` falls inside the window, evaluated in the gym's time zone.
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        let local = time.with_timezone(&super::gym_time_zone());
+        let (day, time) = (local.weekday(), local.time());
+
+        if self.start < self.end {
+            self.days.contains(day.into()) && self.start <= time && time < self.end
+        } else {
+            (self.days.contains(day.into()) && self.start <= time)
+                || (self.days.contains(day.pred().into()) && time < self.end)
+        }
+    }
+}
+
+#[test]
+fn access_window() {
+    use chrono::TimeZone;
+
+    let gym = super::gym_time_zone();
+    let at = |d, h, m| gym.with_ymd_and_hms(2025, 3, d, h, m, 0).unwrap().to_utc();
+
+    // 2025-03-03 is a Monday and 2025-03-08 a Saturday.
+    let off_peak = AccessWindow::off_peak();
+    assert!(off_peak.contains(at(3, 9, 30)));
+    assert!(off_peak.contains(at(3, 15, 59)));
+    assert!(!off_peak.contains(at(3, 16, 0)));
+    assert!(!off_peak.contains(at(8, 9, 30)));
+
+    let late = AccessWindow {
+        days: Weekdays::FRIDAY,
+        start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+        end: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+    };
+    assert!(late.contains(at(7, 23, 0)));
+    assert!(late.contains(at(8, 1, 0)));
+    assert!(!late.contains(at(8, 3, 0)));
+    assert!(!late.contains(at(6, 23, 0)));
+}
@@ -0,0 +1,729 @@
+#![cfg(feature = "sqlite")]
+//! Generic storage of [`Queryable`] records in SQLite, and authorized access to users and passes
+//! on top of it.
+
+use crate::{
+    args::{
+        create::{CreateUserPass, NewUser},
+        query::{QueryPermissionGrant, QueryUser, QueryUserPass},
+        update::Update,
+    },
+    authorize::{
+        authorize, authorize_grant, authorize_privileged, authorize_removal, Action, Actor, Denied,
+        Escalation, Resource,
+    },
+    pass::UserPass,
+    table::{BindValues, Queryable},
+    user::{
+        grant::{audit, GrantAuditEntry, PermissionGrant},
+        permissions::{MissingPermissions, Permissions},
+        User,
+    },
+};
+use chrono::Utc;
+use sqlx::{Executor, Pool, Sqlite, SqliteConnection};
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum StoreError {
+    Database(sqlx::Error),
+    Denied(Denied),
+    Escalation(Escalation),
+    MissingPermissions(MissingPermissions),
+}
+
+impl Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreError::Database(e) => write!(f, "database error: {e}"),
+            StoreError::Denied(e) => e.fmt(f),
+            StoreError::Escalation(e) => e.fmt(f),
+            StoreError::MissingPermissions(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::Database(e) => Some(e),
+            StoreError::Denied(e) => Some(e),
+            StoreError::Escalation(e) => Some(e),
+            StoreError::MissingPermissions(e) => Some(e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for StoreError {
+    fn from(e: sqlx::Error) -> Self {
+        StoreError::Database(e)
+    }
+}
+
+impl From<Denied> for StoreError {
+    fn from(e: Denied) -> Self {
+        StoreError::Denied(e)
+    }
+}
+
+/// For checks made with [`Permissions::require`] before calling the store.
+impl From<MissingPermissions> for StoreError {
+    fn from(e: MissingPermissions) -> Self {
+        StoreError::MissingPermissions(e)
+    }
+}
+
+impl From<Escalation> for StoreError {
+    fn from(e: Escalation) -> Self {
+        StoreError::Escalation(e)
+    }
+}
+
+/// `a = ?, b = ?` for the bound columns of `args`.
+fn assignments(args: &impl BindValues, separator: &str) -> String {
+    args.bound_values()
+        .iter()
+        .map(|column| format!("{column} = ?"))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn filter(args: &impl BindValues) -> String {
+    match args.bound_values().is_empty() {
+        true => String::new(),
+        false => format!(" WHERE {}", assignments(args, " AND ")),
+    }
+}
+
+/// Fetches the records matching every field set in `params` through `executor`.
+async fn fetch_with<'c, T: Queryable>(
+    executor: impl Executor<'c, Database = Sqlite>,
+    params: &T::QueryArgs,
+) -> Result<Vec<T>, sqlx::Error> {
+    let sql = format!("SELECT * FROM \"{}\"{}", T::table_name(), filter(params));
+    let rows = params
+        .bind_values(sqlx::query(&sql))
+        .fetch_all(executor)
+        .await?;
+    rows.iter().map(sqlx::FromRow::from_row).collect()
+}
+
+/// Applies an update through `executor`, returning the number of changed records.
+async fn update_with<'c, T: Queryable>(
+    executor: impl Executor<'c, Database = Sqlite>,
+    update: &Update<T>,
+) -> Result<u64, sqlx::Error> {
+    if update.new_params.bound_values().is_empty() {
+        return Ok(0);
+    }
+    let sql = format!(
+        "UPDATE \"{}\" SET {}{}",
+        T::table_name(),
+        assignments(&update.new_params, ", "),
+        filter(&update.match_params)
+    );
+    let query = update.new_params.bind_values(sqlx::query(&sql));
+    let result = update
+        .match_params
+        .bind_values(query)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Deletes the records matching `params` through `executor`, returning their number.
+async fn delete_with<'c, T: Queryable>(
+    executor: impl Executor<'c, Database = Sqlite>,
+    params: &T::QueryArgs,
+) -> Result<u64, sqlx::Error> {
+    let sql = format!("DELETE FROM \"{}\"{}", T::table_name(), filter(params));
+    let result = params
+        .bind_values(sqlx::query(&sql))
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Checks `action` on every record matching `params`, returning them.
+async fn authorized<'c, T>(
+    executor: impl Executor<'c, Database = Sqlite>,
+    actor: Actor,
+    action: Action,
+    params: &T::QueryArgs,
+) -> Result<Vec<T>, StoreError>
+where
+    T: Queryable,
+    for<'a> &'a T: Into<Resource>,
+{
+    let records = fetch_with::<T>(executor, params).await?;
+    for record in &records {
+        authorize(actor, action, record)?;
+    }
+    Ok(records)
+}
+
+/// Fails unless more root users exist than the `removed` ones.
+async fn keep_a_root(conn: &mut SqliteConnection, removed: usize) -> Result<(), StoreError> {
+    if removed == 0 {
+        return Ok(());
+    }
+    let (roots,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user WHERE permissions = ?")
+        .bind(Permissions::ROOT)
+        .fetch_one(conn)
+        .await?;
+    match roots > removed as i64 {
+        true => Ok(()),
+        false => Err(Escalation::LastRoot.into()),
+    }
+}
+
+/// Matches the user with `id` only.
+fn by_id(id: i32) -> QueryUser {
+    QueryUser {
+        id: Some(id),
+        ..Default::default()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Store {
+    pub pool: Pool<Sqlite>,
+}
+
+impl Store {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    /// Inserts a record, returning it as stored.
+    pub async fn create<T: Queryable>(&self, args: &T::CreateArgs) -> Result<T, sqlx::Error> {
+        let columns = args.bound_values();
+        let sql = format!(
+            "INSERT INTO \"{}\" ({}) VALUES ({}) RETURNING *",
+            T::table_name(),
+            columns.join(", "),
+            vec!["?"; columns.len()].join(", ")
+        );
+        let row = args
+            .bind_values(sqlx::query(&sql))
+            .fetch_one(&self.pool)
+            .await?;
+        sqlx::FromRow::from_row(&row)
+    }
+
+    /// Fetches the records matching every field set in `params`.
+    pub async fn fetch<T: Queryable>(&self, params: &T::QueryArgs) -> Result<Vec<T>, sqlx::Error> {
+        fetch_with(&self.pool, params).await
+    }
+
+    /// Applies an update, returning the number of changed records.
+    pub async fn update<T: Queryable>(&self, update: &Update<T>) -> Result<u64, sqlx::Error> {
+        update_with(&self.pool, update).await
+    }
+
+    /// Deletes the records matching `params`, returning their number.
+    pub async fn delete<T: Queryable>(&self, params: &T::QueryArgs) -> Result<u64, sqlx::Error> {
+        delete_with::<T>(&self.pool, params).await
+    }
+
+    /// Applies [`Permissions::migrate_finance`] to every stored user and role, returning the number
+    /// of changed records. This should be run once when upgrading to the finance flags.
+    pub async fn migrate_finance_permissions(&self) -> Result<u64, sqlx::Error> {
+        let legacy = Permissions::LEGACY_ADMIN.bits();
+        let mut migrated = 0;
+        for table in ["user", "role"] {
+            let exists =
+                sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?")
+                    .bind(table)
+                    .fetch_optional(&self.pool)
+                    .await?;
+            if exists.is_none() {
+                continue;
+            }
+            let sql = format!(
+                "UPDATE \"{table}\" SET permissions = permissions | ?
+                WHERE permissions & ? = ? AND permissions & ? != ? AND permissions != ?"
+            );
+            let result = sqlx::query(&sql)
+                .bind(Permissions::FINANCE)
+                .bind(legacy)
+                .bind(legacy)
+                .bind(Permissions::FINANCE)
+                .bind(Permissions::FINANCE)
+                .bind(Permissions::ROOT)
+                .execute(&self.pool)
+                .await?;
+            migrated += result.rows_affected();
+        }
+        Ok(migrated)
+    }
+
+    /// Creates a user. The actor can only give the new user permissions they hold themselves.
+    pub async fn create_user(&self, actor: Actor, args: &NewUser) -> Result<User, StoreError> {
+        let id = args.id.unwrap_or_default();
+        authorize(actor, Action::Create, Resource::User { id })?;
+        authorize_grant(actor, id, Permissions::NONE, args.permissions)?;
+        Ok(self.create(args).await?)
+    }
+
+    pub async fn users(&self, actor: Actor, params: &QueryUser) -> Result<Vec<User>, StoreError> {
+        authorized(&self.pool, actor, Action::Read, params).await
+    }
+
+    /// Updates users. Changes to their permissions are checked with [`authorize_grant`], and the
+    /// last root user can not be demoted. Users whose email address changes have to verify it
+    /// again. The users are checked and updated in one transaction.
+    pub async fn update_users(
+        &self,
+        actor: Actor,
+        update: &Update<User>,
+    ) -> Result<u64, StoreError> {
+        let mut tx = self.pool.begin().await?;
+        let users =
+            authorized::<User>(&mut *tx, actor, Action::Update, &update.match_params).await?;
+        let QueryUser {
+            id, email_verified, ..
+        } = &update.new_params;
+        if id.is_some() || email_verified.is_some() {
+            for user in &users {
+                authorize_privileged(actor, Action::Update, user)?;
+            }
+        }
+        if let Some(new) = update.new_params.permissions {
+            for user in &users {
+                authorize_grant(actor, user.id, user.permissions, new)?;
+            }
+            let demoted = users
+                .iter()
+                .filter(|user| user.permissions == Permissions::ROOT && new != Permissions::ROOT);
+            keep_a_root(&mut tx, demoted.count()).await?;
+        }
+        let mut updated = 0;
+        for user in &users {
+            let email_verified = match &update.new_params.email {
+                Some(email) if email != &user.email => Some(false),
+                _ => update.new_params.email_verified,
+            };
+            let new_params = QueryUser {
+                email_verified,
+                ..update.new_params.clone()
+            };
+            updated +=
+                update_with(&mut *tx, &Update::<User>::new(by_id(user.id), new_params)).await?;
+        }
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    /// Deletes users. Root users can only be deleted by root, and never the last one. The users are
+    /// checked and deleted in one transaction.
+    pub async fn delete_users(&self, actor: Actor, params: &QueryUser) -> Result<u64, StoreError> {
+        let mut tx = self.pool.begin().await?;
+        let users = authorized::<User>(&mut *tx, actor, Action::Delete, params).await?;
+        for user in &users {
+            authorize_removal(actor, user)?;
+        }
+        let roots = users
+            .iter()
+            .filter(|user| user.permissions == Permissions::ROOT);
+        keep_a_root(&mut tx, roots.count()).await?;
+        let mut deleted = 0;
+        for user in &users {
+            deleted += delete_with::<User>(&mut *tx, &by_id(user.id)).await?;
+        }
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Lists the temporary grants matching `params` with their current status. Users may see the
+    /// grants given to them, otherwise `USER_READ` is needed.
+    pub async fn grant_audit(
+        &self,
+        actor: Actor,
+        params: &QueryPermissionGrant,
+    ) -> Result<Vec<GrantAuditEntry>, StoreError> {
+        let grants = self.fetch::<PermissionGrant>(params).await?;
+        for grant in &grants {
+            authorize(actor, Action::Read, Resource::User { id: grant.user_id })?;
+        }
+        Ok(audit(grants, Utc::now()))
+    }
+
+    pub async fn create_pass(
+        &self,
+        actor: Actor,
+        args: &CreateUserPass,
+    ) -> Result<UserPass, StoreError> {
+        let resource = Resource::UserPass {
+            owner: args.user_id,
+        };
+        authorize(actor, Action::Create, resource)?;
+        Ok(self.create(args).await?)
+    }
+
+    pub async fn passes(
+        &self,
+        actor: Actor,
+        params: &QueryUserPass,
+    ) -> Result<Vec<UserPass>, StoreError> {
+        authorized(&self.pool, actor, Action::Read, params).await
+    }
+
+    /// Updates passes. Changing their balance, expiry or window needs `PASS_UPDATE` even on one's
+    /// own pass, and moving a pass to another user also needs permission to update that user's
+    /// passes.
+    pub async fn update_passes(
+        &self,
+        actor: Actor,
+        update: &Update<UserPass>,
+    ) -> Result<u64, StoreError> {
+        let passes =
+            authorized::<UserPass>(&self.pool, actor, Action::Update, &update.match_params).await?;
+        let QueryUserPass {
+            id,
+            time_pass,
+            session_pass,
+            window,
+            ..
+        } = &update.new_params;
+        if id.is_some() || time_pass.is_some() || session_pass.is_some() || window.is_some() {
+            for pass in &passes {
+                authorize_privileged(actor, Action::Update, pass)?;
+            }
+        }
+        if let Some(owner) = update.new_params.user_id {
+            authorize(actor, Action::Update, Resource::UserPass { owner })?;
+        }
+        Ok(self.update(update).await?)
+    }
+
+    pub async fn delete_passes(
+        &self,
+        actor: Actor,
+        params: &QueryUserPass,
+    ) -> Result<u64, StoreError> {
+        authorized::<UserPass>(&self.pool, actor, Action::Delete, params).await?;
+        Ok(self.delete::<UserPass>(params).await?)
+    }
+}
+
+/// Runs `test` on a store with empty user, pass and grant tables.
+#[cfg(test)]
+fn with_store<F: std::future::Future<Output = ()>>(test: impl FnOnce(Store) -> F) {
+    use crate::table::Table;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        User::init(&pool).await.unwrap();
+        UserPass::init(&pool).await.unwrap();
+        PermissionGrant::init(&pool).await.unwrap();
+        test(Store::new(pool)).await
+    });
+}
+
+#[cfg(test)]
+const ROOT: Actor = Actor {
+    id: 0,
+    permissions: Permissions::ROOT,
+    granted: Permissions::NONE,
+};
+
+#[cfg(test)]
+fn new_user(username: &str) -> NewUser {
+    NewUser {
+        id: None,
+        username: username.into(),
+        email: format!("{username}@example.com").parse().unwrap(),
+        email_verified: false,
+        number: None,
+        password: crate::user::password::PasswordHash::from_raw("hunter2"),
+        permissions: Permissions::NONE,
+    }
+}
+
+#[cfg(test)]
+fn promote(id: crate::user::UserId, permissions: Permissions) -> Update<User> {
+    Update::new(
+        by_id(id),
+        QueryUser {
+            permissions: Some(permissions),
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn authorized_store() {
+    with_store(|store| async move {
+        let jo = store.create_user(ROOT, &new_user("jo")).await.unwrap();
+        let kim = store.create_user(ROOT, &new_user("kim")).await.unwrap();
+        let member = Actor::from(&jo);
+        assert!(matches!(
+            store.create_user(member, &new_user("eve")).await,
+            Err(StoreError::Denied(_))
+        ));
+
+        assert_eq!(
+            store.users(member, &by_id(jo.id)).await.unwrap(),
+            std::slice::from_ref(&jo)
+        );
+        assert!(store.users(member, &Default::default()).await.is_err());
+        assert_eq!(
+            store.users(ROOT, &Default::default()).await.unwrap().len(),
+            2
+        );
+
+        let pass = CreateUserPass {
+            id: 1,
+            user_id: jo.id,
+            time_pass: Default::default(),
+            session_pass: "4".parse().unwrap(),
+            window: None,
+        };
+        assert!(store.create_pass(member, &pass).await.is_err());
+        store.create_pass(ROOT, &pass).await.unwrap();
+
+        let mut update = Update::<UserPass> {
+            match_params: QueryUserPass {
+                id: Some(1),
+                ..Default::default()
+            },
+            new_params: QueryUserPass {
+                session_pass: Some("3".parse().unwrap()),
+                ..Default::default()
+            },
+        };
+        let top_up = store.update_passes(member, &update).await.unwrap_err();
+        assert!(matches!(
+            top_up,
+            StoreError::Denied(Denied {
+                missing: MissingPermissions {
+                    missing: Permissions::PASS_UPDATE
+                },
+                ..
+            })
+        ));
+        assert_eq!(store.update_passes(ROOT, &update).await.unwrap(), 1);
+        update.new_params = QueryUserPass {
+            user_id: Some(kim.id),
+            ..Default::default()
+        };
+        let denied = store.update_passes(member, &update).await.unwrap_err();
+        assert!(matches!(
+            denied,
+            StoreError::Denied(Denied {
+                missing: MissingPermissions {
+                    missing: Permissions::PASS_UPDATE
+                },
+                ..
+            })
+        ));
+        assert!(store
+            .delete_passes(member, &update.match_params)
+            .await
+            .is_err());
+        assert_eq!(
+            store
+                .delete_passes(ROOT, &update.match_params)
+                .await
+                .unwrap(),
+            1
+        );
+    });
+}
+
+#[test]
+fn email_verification() {
+    with_store(|store| async move {
+        let jo = store.create_user(ROOT, &new_user("jo")).await.unwrap();
+        let member = Actor::from(&jo);
+        let own_email = |email: &str| {
+            Update::<User>::new(
+                by_id(jo.id),
+                QueryUser {
+                    email: Some(email.parse().unwrap()),
+                    ..Default::default()
+                },
+            )
+        };
+        let verified = Update::<User>::new(
+            by_id(jo.id),
+            QueryUser {
+                email_verified: Some(true),
+                ..Default::default()
+            },
+        );
+        let jo_verified =
+            || async { store.users(member, &by_id(jo.id)).await.unwrap()[0].email_verified };
+        assert!(matches!(
+            store.update_users(member, &verified).await,
+            Err(StoreError::Denied(_))
+        ));
+        store.update_users(ROOT, &verified).await.unwrap();
+        store
+            .update_users(member, &own_email("jo@example.com"))
+            .await
+            .unwrap();
+        assert!(jo_verified().await);
+        store
+            .update_users(member, &own_email("jo@example.org"))
+            .await
+            .unwrap();
+        assert!(!jo_verified().await);
+    });
+}
+
+#[test]
+fn permission_escalation() {
+    with_store(|store| async move {
+        let jo = store.create_user(ROOT, &new_user("jo")).await.unwrap();
+        let kim = store.create_user(ROOT, &new_user("kim")).await.unwrap();
+        let member = Actor::from(&jo);
+        let admin = NewUser {
+            permissions: Permissions::ADMIN,
+            ..new_user("ada")
+        };
+        let ada = Actor::from(&store.create_user(ROOT, &admin).await.unwrap());
+        assert!(matches!(
+            store.create_user(member, &admin).await,
+            Err(StoreError::Denied(_))
+        ));
+        assert!(matches!(
+            store
+                .update_users(ada, &promote(kim.id, Permissions::ROOT))
+                .await,
+            Err(StoreError::Escalation(Escalation::NotHeld { .. }))
+        ));
+        assert!(matches!(
+            store
+                .update_users(ada, &promote(ada.id, Permissions::ROOT))
+                .await,
+            Err(StoreError::Escalation(Escalation::OwnPermissions { .. }))
+        ));
+        assert!(matches!(
+            store
+                .update_users(member, &promote(jo.id, Permissions::USER_READ))
+                .await,
+            Err(StoreError::Escalation(Escalation::OwnPermissions { .. }))
+        ));
+        store
+            .update_users(ada, &promote(kim.id, Permissions::USER_READ))
+            .await
+            .unwrap();
+
+        let keeper = NewUser {
+            permissions: Permissions::ROOT,
+            ..new_user("root")
+        };
+        let keeper = store.create_user(ROOT, &keeper).await.unwrap();
+        assert!(matches!(
+            store.delete_users(ada, &by_id(keeper.id)).await,
+            Err(StoreError::Escalation(Escalation::RootProtected { .. }))
+        ));
+        assert!(matches!(
+            store
+                .update_users(ROOT, &promote(keeper.id, Permissions::ADMIN))
+                .await,
+            Err(StoreError::Escalation(Escalation::LastRoot))
+        ));
+        assert!(matches!(
+            store.delete_users(ROOT, &by_id(keeper.id)).await,
+            Err(StoreError::Escalation(Escalation::LastRoot))
+        ));
+    });
+}
+
+#[test]
+fn finance_migration() {
+    with_store(|store| async move {
+        let legacy = NewUser {
+            permissions: Permissions::LEGACY_ADMIN,
+            ..new_user("old")
+        };
+        let legacy = store.create_user(ROOT, &legacy).await.unwrap();
+        assert_eq!(store.migrate_finance_permissions().await.unwrap(), 1);
+        let migrated = store.users(ROOT, &by_id(legacy.id)).await.unwrap();
+        assert_eq!(migrated[0].permissions, Permissions::ADMIN);
+    });
+}
+
+#[test]
+fn permission_grants() {
+    with_store(|store| async move {
+        let jo = store.create_user(ROOT, &new_user("jo")).await.unwrap();
+        let kim = store.create_user(ROOT, &new_user("kim")).await.unwrap();
+        let admin = NewUser {
+            permissions: Permissions::ADMIN,
+            ..new_user("ada")
+        };
+        let ada = store.create_user(ROOT, &admin).await.unwrap();
+
+        let weekend = PermissionGrant::issue(
+            &ada,
+            &[],
+            &[],
+            jo.id,
+            Permissions::PASS_READ,
+            Utc::now() - chrono::Duration::days(3),
+            Utc::now() - chrono::Duration::days(1),
+        )
+        .unwrap();
+        store.create::<PermissionGrant>(&weekend).await.unwrap();
+        let audit = store
+            .grant_audit(Actor::from(&jo), &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].status, crate::user::grant::GrantStatus::Expired);
+        assert_eq!(audit[0].grant.granted_by, ada.id);
+        assert!(store
+            .grant_audit(Actor::from(&kim), &Default::default())
+            .await
+            .is_err());
+
+        // A temporary grant can not be turned into permanent permissions of one's own.
+        let volunteer = PermissionGrant::issue(
+            &ada,
+            &[],
+            &[],
+            kim.id,
+            Permissions::ADMIN,
+            Utc::now() - chrono::Duration::hours(1),
+            Utc::now() + chrono::Duration::days(1),
+        )
+        .unwrap();
+        let volunteer = store.create::<PermissionGrant>(&volunteer).await.unwrap();
+        let kim_with_grant = Actor::with_grants(&kim, &[], &[volunteer]);
+        assert!(kim_with_grant.permissions.contains(Permissions::ADMIN));
+        assert!(matches!(
+            store
+                .update_users(kim_with_grant, &promote(kim.id, Permissions::ADMIN))
+                .await,
+            Err(StoreError::Escalation(Escalation::OwnPermissions { .. }))
+        ));
+        // Nor can it be handed out permanently to others.
+        assert!(matches!(
+            store
+                .update_users(kim_with_grant, &promote(jo.id, Permissions::ADMIN))
+                .await,
+            Err(StoreError::Escalation(Escalation::NotHeld { .. }))
+        ));
+        let finance = NewUser {
+            permissions: Permissions::FINANCE,
+            ..new_user("eve")
+        };
+        assert!(matches!(
+            store.create_user(kim_with_grant, &finance).await,
+            Err(StoreError::Escalation(Escalation::NotHeld { .. }))
+        ));
+    });
+}
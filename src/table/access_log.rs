@@ -0,0 +1,37 @@
+use super::Table;
+use crate::pass::log::AccessLogEntry;
+use smol_str::SmolStr;
+
+impl Table for AccessLogEntry {
+    fn table_name() -> SmolStr {
+        SmolStr::from("accesslog")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![
+            SmolStr::from("id"),
+            SmolStr::from("pass_id"),
+            SmolStr::from("user_id"),
+            SmolStr::from("time"),
+            SmolStr::from("method"),
+            SmolStr::from("denial"),
+        ]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS accesslog (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                pass_id BIGINT NOT NULL,
+                user_id INTEGER NOT NULL,
+                time TEXT NOT NULL,
+                method TEXT,
+                denial TEXT
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
@@ -0,0 +1,33 @@
+use super::Table;
+use crate::pass::calendar::Closure;
+use smol_str::SmolStr;
+
+impl Table for Closure {
+    fn table_name() -> SmolStr {
+        SmolStr::from("closure")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![
+            SmolStr::from("id"),
+            SmolStr::from("date"),
+            SmolStr::from("kind"),
+            SmolStr::from("description"),
+        ]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS closure (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                date TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                description TEXT NOT NULL
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
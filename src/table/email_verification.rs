@@ -0,0 +1,35 @@
+use super::Table;
+use crate::user::verification::EmailVerification;
+use smol_str::SmolStr;
+
+impl Table for EmailVerification {
+    fn table_name() -> SmolStr {
+        SmolStr::from("emailverification")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![
+            SmolStr::from("id"),
+            SmolStr::from("user_id"),
+            SmolStr::from("email"),
+            SmolStr::from("token_hash"),
+            SmolStr::from("expiry"),
+        ]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS emailverification (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                email TEXT NOT NULL,
+                token_hash TEXT NOT NULL UNIQUE,
+                expiry TEXT NOT NULL
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
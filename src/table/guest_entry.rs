@@ -0,0 +1,39 @@
+use super::Table;
+use crate::pass::guest::GuestEntry;
+use smol_str::SmolStr;
+
+impl Table for GuestEntry {
+    fn table_name() -> SmolStr {
+        SmolStr::from("guestentry")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![
+            SmolStr::from("id"),
+            SmolStr::from("pass_id"),
+            SmolStr::from("host_id"),
+            SmolStr::from("name"),
+            SmolStr::from("email"),
+            SmolStr::from("time"),
+            SmolStr::from("method"),
+        ]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS guestentry (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                pass_id BIGINT NOT NULL,
+                host_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                email TEXT,
+                time TEXT NOT NULL,
+                method TEXT NOT NULL
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
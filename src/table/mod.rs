@@ -1,7 +1,18 @@
 #![cfg(feature = "sqlite")]
 
+pub mod access_log;
+pub mod closure;
+pub mod email_verification;
+pub mod guest_entry;
+pub mod pass_member;
+pub mod password_reset;
+pub mod permission_grant;
+pub mod recovery_code;
+pub mod role;
+pub mod two_factor;
 pub mod user;
 pub mod user_pass;
+pub mod user_role;
 
 use serde::Serialize;
 use smol_str::SmolStr;
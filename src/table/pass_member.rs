@@ -0,0 +1,27 @@
+use super::Table;
+use crate::pass::group::PassMember;
+use smol_str::SmolStr;
+
+impl Table for PassMember {
+    fn table_name() -> SmolStr {
+        SmolStr::from("passmember")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![SmolStr::from("pass_id"), SmolStr::from("user_id")]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS passmember (
+                pass_id BIGINT NOT NULL,
+                user_id INTEGER NOT NULL,
+                PRIMARY KEY (pass_id, user_id)
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
@@ -0,0 +1,35 @@
+use super::Table;
+use crate::user::reset::PasswordReset;
+use smol_str::SmolStr;
+
+impl Table for PasswordReset {
+    fn table_name() -> SmolStr {
+        SmolStr::from("passwordreset")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![
+            SmolStr::from("id"),
+            SmolStr::from("user_id"),
+            SmolStr::from("token_hash"),
+            SmolStr::from("expiry"),
+            SmolStr::from("used"),
+        ]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS passwordreset (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                token_hash TEXT NOT NULL UNIQUE,
+                expiry TEXT NOT NULL,
+                used BOOLEAN NOT NULL
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
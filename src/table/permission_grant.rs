@@ -0,0 +1,37 @@
+use super::Table;
+use crate::user::grant::PermissionGrant;
+use smol_str::SmolStr;
+
+impl Table for PermissionGrant {
+    fn table_name() -> SmolStr {
+        SmolStr::from("permissiongrant")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![
+            SmolStr::from("id"),
+            SmolStr::from("user_id"),
+            SmolStr::from("permissions"),
+            SmolStr::from("start"),
+            SmolStr::from("end"),
+            SmolStr::from("granted_by"),
+        ]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS permissiongrant (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                permissions INTEGER NOT NULL,
+                start TEXT NOT NULL,
+                end TEXT NOT NULL,
+                granted_by INTEGER NOT NULL
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
@@ -0,0 +1,33 @@
+use super::Table;
+use crate::user::two_factor::RecoveryCode;
+use smol_str::SmolStr;
+
+impl Table for RecoveryCode {
+    fn table_name() -> SmolStr {
+        SmolStr::from("recoverycode")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![
+            SmolStr::from("id"),
+            SmolStr::from("user_id"),
+            SmolStr::from("code_hash"),
+            SmolStr::from("used"),
+        ]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS recoverycode (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                code_hash TEXT NOT NULL UNIQUE,
+                used BOOLEAN NOT NULL
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
@@ -0,0 +1,33 @@
+use super::Table;
+use crate::user::role::Role;
+use smol_str::SmolStr;
+
+impl Table for Role {
+    fn table_name() -> SmolStr {
+        SmolStr::from("role")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![
+            SmolStr::from("id"),
+            SmolStr::from("name"),
+            SmolStr::from("permissions"),
+            SmolStr::from("description"),
+        ]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS role (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                permissions INTEGER NOT NULL,
+                description TEXT NOT NULL
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
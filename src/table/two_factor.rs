@@ -0,0 +1,33 @@
+use super::Table;
+use crate::user::two_factor::TwoFactor;
+use smol_str::SmolStr;
+
+impl Table for TwoFactor {
+    fn table_name() -> SmolStr {
+        SmolStr::from("twofactor")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![
+            SmolStr::from("user_id"),
+            SmolStr::from("secret"),
+            SmolStr::from("enabled"),
+            SmolStr::from("last_step"),
+        ]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS twofactor (
+                user_id INTEGER PRIMARY KEY,
+                secret BLOB NOT NULL,
+                enabled BOOLEAN NOT NULL,
+                last_step INTEGER
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
@@ -15,6 +15,7 @@ impl Table for User {
             "username".into(),
             "number".into(),
             "email".into(),
+            "email_verified".into(),
             "password".into(),
         ]
     }
@@ -26,6 +27,7 @@ impl Table for User {
                 username TEXT NOT NULL,
                 number INTEGER UNIQUE,
                 email TEXT NOT NULL UNIQUE,
+                email_verified BOOLEAN NOT NULL DEFAULT 0,
                 permissions INTEGER NOT NULL,
                 password TEXT NOT NULL
             )",
@@ -13,6 +13,7 @@ impl Table for UserPass {
             SmolStr::from("user_id"),
             SmolStr::from("time_pass"),
             SmolStr::from("session_pass"),
+            SmolStr::from("window"),
         ]
     }
 
@@ -24,7 +25,8 @@ impl Table for UserPass {
                 id BIGINT PRIMARY KEY,
                 user_id INTEGER NOT NULL,
                 time_pass BLOB NOT NULL,
-                session_pass BLOB NOT NULL
+                session_pass BLOB NOT NULL,
+                window BLOB
             )",
         )
         .execute(pool)
@@ -0,0 +1,27 @@
+use super::Table;
+use crate::user::role::UserRole;
+use smol_str::SmolStr;
+
+impl Table for UserRole {
+    fn table_name() -> SmolStr {
+        SmolStr::from("userrole")
+    }
+
+    fn column_names() -> Vec<SmolStr> {
+        vec![SmolStr::from("user_id"), SmolStr::from("role_id")]
+    }
+
+    async fn init(
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS userrole (
+                user_id INTEGER NOT NULL,
+                role_id INTEGER NOT NULL,
+                PRIMARY KEY (user_id, role_id)
+            )",
+        )
+        .execute(pool)
+        .await
+    }
+}
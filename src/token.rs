@@ -0,0 +1,102 @@
+use password_hash::rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// A random secret handed to a user, e.g. in an emailed link. Only its [`TokenHash`] is stored so
+/// that a leaked database can not be used to redeem tokens.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Token(String);
+
+impl Token {
+    /// Generates a token from 32 random bytes.
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let token = Self(hex::encode(bytes));
+        bytes.zeroize();
+        token
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn hash(&self) -> TokenHash {
+        TokenHash::of(&self.0)
+    }
+}
+
+impl From<String> for Token {
+    fn from(token: String) -> Self {
+        Self(token)
+    }
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::fmt::Debug for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Token([redacted])")
+    }
+}
+
+/// The SHA-256 of a [`Token`] as hex. Tokens carry enough entropy that a fast hash is sufficient.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TokenHash(String);
+
+impl TokenHash {
+    fn of(token: &str) -> Self {
+        Self(hex::encode(Sha256::digest(token.as_bytes())))
+    }
+
+    /// Checks in constant time whether `token` hashes to this hash.
+    pub fn matches(&self, token: &str) -> bool {
+        Self::of(token).0.as_bytes().ct_eq(self.0.as_bytes()).into()
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<'q> sqlx::Encode<'q, sqlx::Sqlite> for TokenHash {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <sqlx::Sqlite as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        sqlx::Encode::<'q, sqlx::Sqlite>::encode_by_ref(&self.0, buf)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<'r> sqlx::Decode<'r, sqlx::Sqlite> for TokenHash {
+    fn decode(
+        value: <sqlx::Sqlite as sqlx::Database>::ValueRef<'r>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        Ok(Self(<String as sqlx::Decode<sqlx::Sqlite>>::decode(value)?))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl sqlx::Type<sqlx::Sqlite> for TokenHash {
+    fn type_info() -> sqlx::sqlite::SqliteTypeInfo {
+        <String as sqlx::Type<sqlx::Sqlite>>::type_info()
+    }
+}
+
+#[test]
+fn token() {
+    let token = Token::generate();
+    assert_eq!(token.as_str().len(), 64);
+    assert_ne!(token, Token::generate());
+
+    let hash = token.hash();
+    assert!(hash.matches(token.as_str()));
+    assert!(!hash.matches(Token::generate().as_str()));
+    assert!(!format!("{token:?}").contains(token.as_str()));
+}
@@ -0,0 +1,287 @@
+use super::{permissions::Permissions, role::Role, User, UserId};
+use crate::authorize::{authorize_grant, Action, Actor, Denied, Escalation, Resource};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::table::{BindValues, Queryable},
+    backend_proc_macro::BindValues,
+};
+
+pub type PermissionGrantId = i64;
+
+/// Permissions given to a user for a limited time, e.g. admin rights for a volunteer over a
+/// weekend. A grant only counts towards the user's effective permissions in `start..end`, and is
+/// kept afterwards as a record of who granted what.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct PermissionGrant {
+    pub id: PermissionGrantId,
+    pub user_id: UserId,
+    pub permissions: Permissions,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub granted_by: UserId,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for PermissionGrant {
+    type CreateArgs = crate::args::create::CreatePermissionGrant;
+    type QueryArgs = crate::args::query::QueryPermissionGrant;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantStatus {
+    Pending,
+    Active,
+    Expired,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GrantError {
+    /// The actor may not update the user.
+    Denied(Denied),
+    /// The actor does not hold the permissions they tried to grant.
+    Escalation(Escalation),
+    /// `end` is not after `start`, or the issuer's own grant ends before `start`.
+    EmptyPeriod,
+}
+
+impl Display for GrantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrantError::Denied(e) => e.fmt(f),
+            GrantError::Escalation(e) => e.fmt(f),
+            GrantError::EmptyPeriod => f.write_str("a grant has to end after it starts"),
+        }
+    }
+}
+
+impl std::error::Error for GrantError {}
+
+impl PermissionGrant {
+    /// Checks that `issuer`, who has `roles` and `grants` of their own, may give `permissions` to
+    /// `user_id` for `start..end`, returning the args to store.
+    ///
+    /// This needs `USER_UPDATE`, even for the issuer's own account, and only root may grant
+    /// themselves anything. The permissions handed out have to be held without any grant, so
+    /// grants can not be passed on and outlive the grant they came from. If `USER_UPDATE` itself
+    /// comes from a grant, the new grant ends when that one does at the latest.
+    pub fn issue(
+        issuer: &User,
+        roles: &[Role],
+        grants: &[PermissionGrant],
+        user_id: UserId,
+        permissions: Permissions,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<crate::args::create::CreatePermissionGrant, GrantError> {
+        let now = Utc::now();
+        let actor = Actor::with_grants(issuer, roles, grants);
+        let resource = Resource::User { id: user_id };
+        let needed = resource.needed(Action::Update);
+        actor.permissions.require(needed).map_err(|missing| {
+            GrantError::Denied(Denied {
+                action: Action::Update,
+                resource,
+                missing,
+            })
+        })?;
+        authorize_grant(actor, user_id, Permissions::NONE, permissions)
+            .map_err(GrantError::Escalation)?;
+
+        let authority = grants
+            .iter()
+            .filter(|grant| grant.user_id == issuer.id && grant.is_active_at(now))
+            .filter(|grant| grant.permissions.contains(needed))
+            .map(|grant| grant.end)
+            .max();
+        let end = match actor.base().contains(needed) {
+            true => end,
+            false => authority.map_or(end, |authority| end.min(authority)),
+        };
+        if end <= start {
+            return Err(GrantError::EmptyPeriod);
+        }
+        Ok(crate::args::create::CreatePermissionGrant {
+            id: None,
+            user_id,
+            permissions,
+            start,
+            end,
+            granted_by: issuer.id,
+        })
+    }
+
+    pub fn status_at(&self, time: DateTime<Utc>) -> GrantStatus {
+        if time < self.start {
+            GrantStatus::Pending
+        } else if time < self.end {
+            GrantStatus::Active
+        } else {
+            GrantStatus::Expired
+        }
+    }
+
+    pub fn is_active_at(&self, time: DateTime<Utc>) -> bool {
+        self.status_at(time) == GrantStatus::Active
+    }
+}
+
+/// A grant with its status, as listed in an audit of who was given what.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GrantAuditEntry {
+    #[serde(flatten)]
+    pub grant: PermissionGrant,
+    pub status: GrantStatus,
+}
+
+/// Lists `grants` with their status at `time`, most recent first.
+pub fn audit(grants: Vec<PermissionGrant>, time: DateTime<Utc>) -> Vec<GrantAuditEntry> {
+    let mut entries: Vec<_> = grants
+        .into_iter()
+        .map(|grant| GrantAuditEntry {
+            status: grant.status_at(time),
+            grant,
+        })
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.grant.start));
+    entries
+}
+
+impl User {
+    /// The permissions the user holds at `time`: those of [`User::effective_permissions`] and
+    /// those of the `grants` active at `time`. Grants for other users are ignored.
+    pub fn effective_permissions_at(
+        &self,
+        roles: &[Role],
+        grants: &[PermissionGrant],
+        time: DateTime<Utc>,
+    ) -> Permissions {
+        grants
+            .iter()
+            .filter(|grant| grant.user_id == self.id && grant.is_active_at(time))
+            .fold(self.effective_permissions(roles), |permissions, grant| {
+                permissions | grant.permissions
+            })
+    }
+
+    /// Like [`User::effective_permissions_at`], now.
+    pub fn effective_permissions_with_grants(
+        &self,
+        roles: &[Role],
+        grants: &[PermissionGrant],
+    ) -> Permissions {
+        self.effective_permissions_at(roles, grants, Utc::now())
+    }
+}
+
+#[test]
+fn permission_grants() {
+    use chrono::Duration;
+
+    let now = Utc::now();
+    let admin = User {
+        id: 1,
+        permissions: Permissions::ADMIN,
+        ..Default::default()
+    };
+    let volunteer = User {
+        id: 2,
+        ..Default::default()
+    };
+    let stored = |args: crate::args::create::CreatePermissionGrant| PermissionGrant {
+        id: 1,
+        user_id: args.user_id,
+        permissions: args.permissions,
+        start: args.start,
+        end: args.end,
+        granted_by: args.granted_by,
+    };
+
+    let grant = stored(
+        PermissionGrant::issue(
+            &admin,
+            &[],
+            &[],
+            volunteer.id,
+            Permissions::USER_READ | Permissions::USER_UPDATE,
+            now - Duration::days(1),
+            now + Duration::days(1),
+        )
+        .unwrap(),
+    );
+    assert_eq!(grant.granted_by, 1);
+    let grants = std::slice::from_ref(&grant);
+    assert_eq!(
+        volunteer.effective_permissions_with_grants(&[], grants),
+        Permissions::USER_READ | Permissions::USER_UPDATE
+    );
+    assert_eq!(volunteer.effective_permissions(&[]), Permissions::NONE);
+    assert_eq!(
+        volunteer.effective_permissions_at(&[], grants, now + Duration::days(2)),
+        Permissions::NONE
+    );
+
+    let issue = |issuer: &User, grants: &[PermissionGrant], user_id, permissions, days| {
+        PermissionGrant::issue(
+            issuer,
+            &[],
+            grants,
+            user_id,
+            permissions,
+            now,
+            now + Duration::days(days),
+        )
+    };
+    assert!(matches!(
+        issue(&volunteer, &[], 3, Permissions::NONE, 1),
+        Err(GrantError::Denied(_))
+    ));
+    assert!(matches!(
+        issue(&admin, &[], 2, Permissions::ROOT, 1),
+        Err(GrantError::Escalation(Escalation::NotHeld { .. }))
+    ));
+    assert!(matches!(
+        issue(&admin, &[], 2, Permissions::USER_READ, 0),
+        Err(GrantError::EmptyPeriod)
+    ));
+
+    // Nobody but root can grant themselves anything, and granted permissions can not be passed
+    // on, not even back to the volunteer with a later end.
+    assert!(matches!(
+        issue(&admin, &[], admin.id, Permissions::USER_READ, 1),
+        Err(GrantError::Escalation(Escalation::OwnPermissions { id: 1 }))
+    ));
+    assert!(matches!(
+        issue(&volunteer, grants, 3, Permissions::USER_READ, 30),
+        Err(GrantError::Escalation(Escalation::NotHeld { .. }))
+    ));
+    assert!(matches!(
+        issue(&volunteer, grants, volunteer.id, Permissions::USER_READ, 30),
+        Err(GrantError::Escalation(Escalation::OwnPermissions { id: 2 }))
+    ));
+
+    // Authority which comes from a grant ends with it.
+    let desk = User {
+        permissions: Permissions::PASS_READ,
+        ..volunteer.clone()
+    };
+    let capped = issue(&desk, grants, 3, Permissions::PASS_READ, 30).unwrap();
+    assert_eq!(capped.end, grant.end);
+
+    let later = PermissionGrant {
+        id: 2,
+        start: now + Duration::days(7),
+        end: now + Duration::days(9),
+        ..grant.clone()
+    };
+    let entries = audit(vec![grant, later], now + Duration::days(3));
+    assert_eq!(
+        entries.iter().map(|e| e.status).collect::<Vec<_>>(),
+        [GrantStatus::Pending, GrantStatus::Expired]
+    );
+}
@@ -0,0 +1,283 @@
+use super::password::{PasswordHash, Verified, VerifyError};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2, Params, ParamsBuilder,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, sync::OnceLock};
+use zeroize::Zeroize;
+
+static GLOBAL: OnceLock<HasherConfig> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    Argon2d,
+    Argon2i,
+    Argon2id,
+}
+
+impl From<Algorithm> for argon2::Algorithm {
+    fn from(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Argon2d => argon2::Algorithm::Argon2d,
+            Algorithm::Argon2i => argon2::Algorithm::Argon2i,
+            Algorithm::Argon2id => argon2::Algorithm::Argon2id,
+        }
+    }
+}
+
+/// A server side secret mixed into every hash, so that a leaked database alone is not enough to
+/// crack passwords. The `id` is stored in the hash to find the right pepper after rotating it and
+/// may be at most 8 bytes long.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pepper {
+    pub id: String,
+    pub secret: String,
+}
+
+impl Drop for Pepper {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl std::fmt::Debug for Pepper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pepper")
+            .field("id", &self.id)
+            .field("secret", &"[redacted]")
+            .finish()
+    }
+}
+
+/// How passwords are hashed and verified.
+///
+/// The default matches the parameters used before the hasher was configurable, so existing hashes
+/// remain current.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HasherConfig {
+    pub algorithm: Algorithm,
+    /// Memory cost in KiB.
+    pub memory_cost: u32,
+    /// Number of iterations.
+    pub time_cost: u32,
+    /// Degree of parallelism.
+    pub parallelism: u32,
+    /// The pepper used for new hashes.
+    pub pepper: Option<Pepper>,
+    /// Peppers which have been rotated out but are still accepted when verifying. Hashes using them
+    /// are reported as outdated.
+    #[serde(default)]
+    pub retired_peppers: Vec<Pepper>,
+}
+
+impl Default for HasherConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::Argon2id,
+            memory_cost: Params::DEFAULT_M_COST,
+            time_cost: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+            pepper: None,
+            retired_peppers: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HasherConfigError {
+    /// The cost parameters are out of range for the algorithm.
+    InvalidParams(String),
+    /// The pepper id is longer than 8 bytes or the secret too long.
+    InvalidPepper(String),
+    /// [`HasherConfig::install`] was called more than once.
+    AlreadyInstalled,
+}
+
+impl Display for HasherConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HasherConfigError::InvalidParams(e) => write!(f, "invalid hashing parameters: {e}"),
+            HasherConfigError::InvalidPepper(id) => write!(f, "invalid pepper {id:?}"),
+            HasherConfigError::AlreadyInstalled => {
+                f.write_str("a hasher config is already installed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HasherConfigError {}
+
+impl HasherConfig {
+    /// Makes `self` the configuration used by [`PasswordHash`] and [`PlainPassword`]. This can only
+    /// be done once and should happen at startup, before any password is hashed.
+    ///
+    /// [`PlainPassword`]: super::password::PlainPassword
+    pub fn install(self) -> Result<(), HasherConfigError> {
+        self.validate()?;
+        GLOBAL
+            .set(self)
+            .map_err(|_| HasherConfigError::AlreadyInstalled)
+    }
+
+    /// The installed configuration, or the default if none was installed.
+    pub fn global() -> &'static HasherConfig {
+        GLOBAL.get_or_init(HasherConfig::default)
+    }
+
+    /// Checks that passwords can be hashed with this configuration.
+    pub fn validate(&self) -> Result<(), HasherConfigError> {
+        for pepper in self.pepper.iter().chain(&self.retired_peppers) {
+            self.argon2(Some(pepper))?;
+        }
+        self.argon2(None).map(|_| ())
+    }
+
+    fn params(&self, pepper: Option<&Pepper>) -> Result<Params, HasherConfigError> {
+        let mut builder = ParamsBuilder::new();
+        builder
+            .m_cost(self.memory_cost)
+            .t_cost(self.time_cost)
+            .p_cost(self.parallelism);
+        if let Some(pepper) = pepper {
+            let id = pepper.id.as_bytes().try_into();
+            builder.keyid(id.map_err(|_| HasherConfigError::InvalidPepper(pepper.id.clone()))?);
+        }
+        builder
+            .build()
+            .map_err(|e| HasherConfigError::InvalidParams(e.to_string()))
+    }
+
+    fn argon2<'a>(&self, pepper: Option<&'a Pepper>) -> Result<Argon2<'a>, HasherConfigError> {
+        let params = self.params(pepper)?;
+        let (algorithm, version) = (self.algorithm.into(), argon2::Version::default());
+        match pepper {
+            Some(pepper) => {
+                Argon2::new_with_secret(pepper.secret.as_bytes(), algorithm, version, params)
+                    .map_err(|_| HasherConfigError::InvalidPepper(pepper.id.clone()))
+            }
+            None => Ok(Argon2::new(algorithm, version, params)),
+        }
+    }
+
+    fn find_pepper(&self, id: &[u8]) -> Option<&Pepper> {
+        self.pepper
+            .iter()
+            .chain(&self.retired_peppers)
+            .find(|pepper| pepper.id.as_bytes() == id)
+    }
+
+    /// Hashes a plain password with the configured algorithm, costs and pepper.
+    pub fn hash(&self, password: impl AsRef<[u8]>) -> Result<PasswordHash, HasherConfigError> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = self
+            .argon2(self.pepper.as_ref())?
+            .hash_password(password.as_ref(), &salt)
+            .map_err(|e| HasherConfigError::InvalidParams(e.to_string()))?;
+        Ok(PasswordHash {
+            data: hash.to_string(),
+        })
+    }
+
+    /// Checks a plain password against `hash`, using whichever pepper the hash was created with.
+    pub fn verify(
+        &self,
+        hash: &PasswordHash,
+        candidate: impl AsRef<[u8]>,
+    ) -> Result<Verified, VerifyError> {
+        let parsed =
+            argon2::PasswordHash::new(&hash.data).map_err(|_| VerifyError::MalformedHash)?;
+        let params = Params::try_from(&parsed).map_err(|_| VerifyError::MalformedHash)?;
+        let pepper = match params.keyid() {
+            [] => None,
+            id => Some(self.find_pepper(id).ok_or(VerifyError::UnknownPepper)?),
+        };
+        let argon2 = match pepper {
+            Some(pepper) => Argon2::new_with_secret(
+                pepper.secret.as_bytes(),
+                Default::default(),
+                Default::default(),
+                params,
+            )
+            .map_err(|_| VerifyError::UnknownPepper)?,
+            None => Argon2::from(params),
+        };
+
+        argon2
+            .verify_password(candidate.as_ref(), &parsed)
+            .map_err(|e| match e {
+                argon2::password_hash::Error::Password => VerifyError::WrongPassword,
+                _ => VerifyError::MalformedHash,
+            })?;
+
+        Ok(match self.is_outdated(hash) {
+            true => Verified::Outdated,
+            false => Verified::Current,
+        })
+    }
+
+    /// Returns true if `hash` was not produced with this configuration's algorithm, costs and
+    /// current pepper. Hashes which can not be parsed are always outdated.
+    pub fn is_outdated(&self, hash: &PasswordHash) -> bool {
+        let Ok(parsed) = argon2::PasswordHash::new(&hash.data) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&parsed) else {
+            return true;
+        };
+        let pepper_id = self.pepper.as_ref().map_or(&[][..], |p| p.id.as_bytes());
+
+        parsed.algorithm != argon2::Algorithm::from(self.algorithm).ident()
+            || parsed.version != Some(argon2::Version::default().into())
+            || params.m_cost() != self.memory_cost
+            || params.t_cost() != self.time_cost
+            || params.p_cost() != self.parallelism
+            || params.keyid() != pepper_id
+    }
+}
+
+#[test]
+fn hasher_config() {
+    let pepper = |id: &str| Pepper {
+        id: id.into(),
+        secret: format!("{id} secret"),
+    };
+    let cheap = HasherConfig {
+        memory_cost: 1024,
+        time_cost: 1,
+        pepper: Some(pepper("2024")),
+        ..Default::default()
+    };
+    let rotated = HasherConfig {
+        pepper: Some(pepper("2025")),
+        retired_peppers: vec![pepper("2024")],
+        ..cheap.clone()
+    };
+
+    let hash = cheap.hash("hunter2").unwrap();
+    assert!(hash.data.contains("m=1024,t=1,p=1,keyid="));
+    assert_eq!(cheap.verify(&hash, "hunter2"), Ok(Verified::Current));
+    assert_eq!(
+        cheap.verify(&hash, "hunter3"),
+        Err(VerifyError::WrongPassword)
+    );
+    assert_eq!(rotated.verify(&hash, "hunter2"), Ok(Verified::Outdated));
+    assert_eq!(
+        HasherConfig::default().verify(&hash, "hunter2"),
+        Err(VerifyError::UnknownPepper)
+    );
+
+    let unpeppered = HasherConfig {
+        pepper: None,
+        ..cheap.clone()
+    };
+    let hash = unpeppered.hash("hunter2").unwrap();
+    assert_eq!(cheap.verify(&hash, "hunter2"), Ok(Verified::Outdated));
+
+    assert!(HasherConfig {
+        pepper: Some(pepper("much too long")),
+        ..Default::default()
+    }
+    .validate()
+    .is_err());
+}
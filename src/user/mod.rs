@@ -1,6 +1,14 @@
+pub mod grant;
+pub mod hasher;
 pub mod password;
+pub mod password_async;
+pub mod password_policy;
 pub mod permissions;
+pub mod reset;
+pub mod role;
 pub mod sqlx_impl;
+pub mod two_factor;
+pub mod verification;
 
 use crate::email::EmailAddr;
 use password::PasswordHash;
@@ -24,6 +32,10 @@ pub struct User {
     pub id: UserId,
     pub username: String,
     pub email: EmailAddr,
+    /// Whether the user has confirmed that they own `email`, see
+    /// [`EmailVerification`](verification::EmailVerification).
+    #[serde(default)]
+    pub email_verified: bool,
     pub number: Option<PhoneNumber>,
     pub password: PasswordHash,
     pub permissions: Permissions,
@@ -31,7 +43,7 @@ pub struct User {
 
 #[cfg(feature = "sqlite")]
 impl Queryable for User {
-    type CreateArgs = crate::args::create::CreateUser;
+    type CreateArgs = crate::args::create::NewUser;
     type QueryArgs = crate::args::query::QueryUser;
 }
 
@@ -41,6 +53,7 @@ impl Default for User {
             id: 0,
             username: String::new(),
             email: "default@email.com".parse().unwrap(),
+            email_verified: false,
             number: None,
             password: PasswordHash::from_raw(""),
             permissions: Permissions::NONE,
@@ -48,15 +61,18 @@ impl Default for User {
     }
 }
 
+/// Redacts the password. See [`View`](crate::view::View) to also leave out the fields a viewer may
+/// not see.
 impl Serialize for User {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("User", 5)?;
+        let mut state = serializer.serialize_struct("User", 7)?;
         state.serialize_field("id", &self.id)?;
         state.serialize_field("username", &self.username)?;
         state.serialize_field("email", &self.email)?;
+        state.serialize_field("email_verified", &self.email_verified)?;
         state.serialize_field("number", &self.number)?;
         state.serialize_field("permissions", &self.permissions)?;
         state.serialize_field("password", &"[REDACTED]")?;
@@ -70,6 +86,7 @@ impl std::fmt::Debug for User {
             .field("id", &self.id)
             .field("username", &self.username)
             .field("email", &self.email)
+            .field("email_verified", &self.email_verified)
             .field("phone_number", &self.number)
             .field("permissions", &self.permissions)
             .field("password", &"[redacted]")
@@ -1,19 +1,71 @@
-use password_auth::generate_hash;
+use super::hasher::HasherConfig;
 use serde::{Deserialize, Serialize};
-use std::{convert::Infallible, str::FromStr};
+use std::{convert::Infallible, fmt::Display, str::FromStr};
+use zeroize::Zeroize;
 
+/// A password as typed by the user. It is wiped from memory when dropped and can not be
+/// serialized; hash it with [`PlainPassword::hash`] before storing it.
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct PlainPassword(String);
+
+impl PlainPassword {
+    pub fn new(password: impl Into<String>) -> Self {
+        Self(password.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Hashes the password for storage.
+    pub fn hash(&self) -> PasswordHash {
+        PasswordHash::from_raw(self)
+    }
+}
+
+impl Drop for PlainPassword {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::fmt::Debug for PlainPassword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PlainPassword([redacted])")
+    }
+}
+
+impl AsRef<[u8]> for PlainPassword {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl FromStr for PlainPassword {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+/// A stored password hash in PHC string format. Serializing and deserializing it round trips the
+/// hash, it never hashes its input; use [`PlainPassword`] for passwords entered by users.
 #[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(transparent)]
 #[cfg_attr(feature = "sqlite", derive(sqlx::FromRow))]
 pub struct PasswordHash {
     pub(crate) data: String,
 }
 
 impl PasswordHash {
-    /// Takes a plain password and hashes it
+    /// Takes a plain password and hashes it with the [global](HasherConfig::global) hasher
+    /// configuration.
     pub fn from_raw(password: impl AsRef<[u8]>) -> Self {
-        Self {
-            data: generate_hash(password),
-        }
+        HasherConfig::global()
+            .hash(password)
+            .expect("the global hasher config is validated when installed")
     }
 
     /// # Safety
@@ -24,20 +76,63 @@ impl PasswordHash {
             data: String::from_utf8_unchecked(password.as_ref().to_vec()),
         }
     }
+
+    /// Checks a plain password against the hash. On success the result says whether the hash
+    /// should be replaced because it was produced with outdated parameters.
+    pub fn verify(&self, candidate: impl AsRef<[u8]>) -> Result<Verified, VerifyError> {
+        HasherConfig::global().verify(self, candidate)
+    }
+
+    /// Returns true if the hash was not produced with the current algorithm, parameters and
+    /// pepper. Hashes which can not be parsed are always outdated.
+    pub fn is_outdated(&self) -> bool {
+        HasherConfig::global().is_outdated(self)
+    }
+
+    /// Verifies `candidate` and, if the stored hash is outdated, returns a fresh hash of it which
+    /// should be persisted in place of this one.
+    pub fn verify_and_rehash(
+        &self,
+        candidate: impl AsRef<[u8]>,
+    ) -> Result<Option<PasswordHash>, VerifyError> {
+        match self.verify(candidate.as_ref())? {
+            Verified::Current => Ok(None),
+            Verified::Outdated => Ok(Some(Self::from_raw(candidate))),
+        }
+    }
 }
 
-impl<'de> Deserialize<'de> for PasswordHash {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        let password_raw = String::deserialize(deserializer)?;
-        Ok(Self {
-            data: generate_hash(password_raw),
-        })
+/// The outcome of a successful [`PasswordHash::verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verified {
+    /// The password matched and the hash uses the current parameters.
+    Current,
+    /// The password matched but the hash should be regenerated and persisted.
+    Outdated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The password does not match the hash.
+    WrongPassword,
+    /// The stored hash is not a valid PHC string.
+    MalformedHash,
+    /// The hash was peppered with a pepper which is no longer configured.
+    UnknownPepper,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::WrongPassword => f.write_str("the password is incorrect"),
+            VerifyError::MalformedHash => f.write_str("the stored password hash is malformed"),
+            VerifyError::UnknownPepper => f.write_str("the password hash uses an unknown pepper"),
+        }
     }
 }
 
+impl std::error::Error for VerifyError {}
+
 impl AsRef<str> for PasswordHash {
     fn as_ref(&self) -> &str {
         self.data.as_ref()
@@ -50,12 +145,74 @@ impl AsRef<[u8]> for PasswordHash {
     }
 }
 
+/// The string given as a [`PasswordHash`] is not a valid PHC string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MalformedHash;
+
+impl Display for MalformedHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("not a valid PHC password hash")
+    }
+}
+
+impl std::error::Error for MalformedHash {}
+
 impl FromStr for PasswordHash {
-    type Err = Infallible;
+    type Err = MalformedHash;
 
+    /// Wraps a PHC string after checking that it parses. Use [`PlainPassword::hash`] to hash a
+    /// password. Hashes read back from the database are trusted and skip this check.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {
-            data: generate_hash(s),
-        })
+        argon2::PasswordHash::new(s).map_err(|_| MalformedHash)?;
+        Ok(Self { data: s.into() })
     }
 }
+
+impl<'de> Deserialize<'de> for PasswordHash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[test]
+fn password_verify() {
+    let hash = PasswordHash::from_raw("hunter2");
+    assert_eq!(hash.verify("hunter2"), Ok(Verified::Current));
+    assert_eq!(hash.verify("hunter3"), Err(VerifyError::WrongPassword));
+    assert_eq!(hash.verify_and_rehash("hunter2"), Ok(None));
+
+    // Argon2i with a single pass is no longer the default.
+    let old = PasswordHash {
+        data: "$argon2i$v=19$m=65536,t=1,p=1$c29tZXNhbHQAAAAAAAAAAA$+r0d29hqEB0yasKr55ZgICsQGSkl0v0kgwhd+U3wyRo".into(),
+    };
+    assert_eq!(old.verify("password"), Ok(Verified::Outdated));
+    let new = old.verify_and_rehash("password").unwrap().unwrap();
+    assert_eq!(new.verify("password"), Ok(Verified::Current));
+
+    let plain = unsafe { PasswordHash::with_no_hash("hunter2") };
+    assert_eq!(plain.verify("hunter2"), Err(VerifyError::MalformedHash));
+}
+
+#[test]
+fn password_serde() {
+    let hash = PlainPassword::new("hunter2").hash();
+    let json = format!("\"{}\"", hash.data);
+
+    let round_trip: PasswordHash = serde_json::from_str(&json).unwrap();
+    assert_eq!(round_trip, hash);
+    assert_eq!(serde_json::to_string(&round_trip).unwrap(), json);
+    assert_eq!(hash.data.parse::<PasswordHash>(), Ok(hash.clone()));
+
+    assert_eq!("hunter2".parse::<PasswordHash>(), Err(MalformedHash));
+    assert!(serde_json::from_str::<PasswordHash>("\"hunter2\"").is_err());
+
+    let plain: PlainPassword = serde_json::from_str("\"hunter2\"").unwrap();
+    assert_eq!(plain.as_str(), "hunter2");
+    assert!(hash.verify(&plain).is_ok());
+    assert!(!format!("{plain:?}").contains("hunter2"));
+}
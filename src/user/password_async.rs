@@ -0,0 +1,67 @@
+#![cfg(feature = "async")]
+//! Hashing is deliberately slow, so these helpers move it onto tokio's blocking thread pool
+//! instead of stalling the async runtime. Request bodies should carry a [`PlainPassword`], which
+//! deserializes without hashing, and be hashed with these helpers afterwards.
+
+use super::password::{PasswordHash, PlainPassword, Verified, VerifyError};
+use crate::args::create::{CreateUser, NewUser};
+
+/// Runs `f` on the blocking pool, resuming any panic on the calling task.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => panic!("password hashing task failed: {e}"),
+    }
+}
+
+impl PlainPassword {
+    /// Like [`PlainPassword::hash`] but runs on the blocking pool.
+    pub async fn hash_async(self) -> PasswordHash {
+        blocking(move || self.hash()).await
+    }
+}
+
+impl PasswordHash {
+    /// Like [`PasswordHash::verify`] but runs on the blocking pool.
+    pub async fn verify_async(&self, candidate: PlainPassword) -> Result<Verified, VerifyError> {
+        let hash = self.clone();
+        blocking(move || hash.verify(candidate)).await
+    }
+
+    /// Like [`PasswordHash::verify_and_rehash`] but runs on the blocking pool.
+    pub async fn verify_and_rehash_async(
+        &self,
+        candidate: PlainPassword,
+    ) -> Result<Option<PasswordHash>, VerifyError> {
+        let hash = self.clone();
+        blocking(move || hash.verify_and_rehash(candidate)).await
+    }
+}
+
+impl CreateUser {
+    /// Like [`CreateUser::hash_password`] but runs on the blocking pool.
+    pub async fn hash_password_async(self) -> NewUser {
+        blocking(move || self.hash_password()).await
+    }
+}
+
+#[test]
+fn password_async() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let hash = PlainPassword::new("hunter2").hash_async().await;
+        assert_eq!(
+            hash.verify_async(PlainPassword::new("hunter2")).await,
+            Ok(Verified::Current)
+        );
+        assert_eq!(
+            hash.verify_and_rehash_async(PlainPassword::new("hunter3"))
+                .await,
+            Err(VerifyError::WrongPassword)
+        );
+    });
+}
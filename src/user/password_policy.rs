@@ -0,0 +1,214 @@
+use super::password::PlainPassword;
+use crate::email::EmailAddr;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt::Display, path::Path};
+use zeroize::Zeroizing;
+
+/// Rules a new password has to follow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PasswordPolicy {
+    /// The minimum number of characters.
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    /// Requires a character which is neither a letter nor a digit.
+    pub require_symbol: bool,
+    /// Passwords which are refused outright, stored in lowercase.
+    pub banned: HashSet<String>,
+}
+
+impl Default for PasswordPolicy {
+    /// At least 8 characters of any kind.
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            banned: HashSet::new(),
+        }
+    }
+}
+
+/// A rule of the [`PasswordPolicy`] which a password broke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum PolicyViolation {
+    TooShort { min_length: usize },
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    Banned,
+    SameAsUsername,
+    SameAsEmail,
+}
+
+impl Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyViolation::TooShort { min_length } => {
+                write!(f, "must be at least {min_length} characters long")
+            }
+            PolicyViolation::MissingLowercase => f.write_str("must contain a lowercase letter"),
+            PolicyViolation::MissingUppercase => f.write_str("must contain an uppercase letter"),
+            PolicyViolation::MissingDigit => f.write_str("must contain a digit"),
+            PolicyViolation::MissingSymbol => f.write_str("must contain a symbol"),
+            PolicyViolation::Banned => f.write_str("is too common"),
+            PolicyViolation::SameAsUsername => f.write_str("must not be your username"),
+            PolicyViolation::SameAsEmail => f.write_str("must not be your email address"),
+        }
+    }
+}
+
+/// Every rule a password broke.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PolicyViolations(pub Vec<PolicyViolation>);
+
+impl Display for PolicyViolations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the password ")?;
+        for (i, violation) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PolicyViolations {}
+
+impl PasswordPolicy {
+    /// Adds `words` to the banned passwords.
+    pub fn with_banned(mut self, words: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.banned
+            .extend(words.into_iter().map(|word| word.as_ref().to_lowercase()));
+        self
+    }
+
+    /// Adds the passwords in the word list at `path` to the banned passwords. The list has one
+    /// password per line, blank lines and lines starting with `#` are skipped.
+    pub fn with_banned_list(self, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let list = std::fs::read_to_string(path)?;
+        Ok(self.with_banned(
+            list.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        ))
+    }
+
+    /// Checks `password` for a user with `username` and `email` against every rule.
+    pub fn check(
+        &self,
+        password: &str,
+        username: &str,
+        email: &EmailAddr,
+    ) -> Result<(), PolicyViolations> {
+        let lowercase = Zeroizing::new(password.to_lowercase());
+        let email: &str = email.as_ref();
+        let email_local = email.rsplit_once('@').map_or(email, |(local, _)| local);
+
+        let violations: Vec<_> = [
+            (
+                password.chars().count() < self.min_length,
+                PolicyViolation::TooShort {
+                    min_length: self.min_length,
+                },
+            ),
+            (
+                self.require_lowercase && !password.chars().any(char::is_lowercase),
+                PolicyViolation::MissingLowercase,
+            ),
+            (
+                self.require_uppercase && !password.chars().any(char::is_uppercase),
+                PolicyViolation::MissingUppercase,
+            ),
+            (
+                self.require_digit && !password.chars().any(|c| c.is_ascii_digit()),
+                PolicyViolation::MissingDigit,
+            ),
+            (
+                self.require_symbol && password.chars().all(char::is_alphanumeric),
+                PolicyViolation::MissingSymbol,
+            ),
+            (self.banned.contains(&*lowercase), PolicyViolation::Banned),
+            (
+                *lowercase == username.to_lowercase(),
+                PolicyViolation::SameAsUsername,
+            ),
+            (
+                *lowercase == email.to_lowercase() || *lowercase == email_local.to_lowercase(),
+                PolicyViolation::SameAsEmail,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(violated, violation)| violated.then_some(violation))
+        .collect();
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(PolicyViolations(violations)),
+        }
+    }
+}
+
+impl PlainPassword {
+    /// Creates a password for a user with `username` and `email` if it follows `policy`.
+    pub fn with_policy(
+        password: impl Into<String>,
+        policy: &PasswordPolicy,
+        username: &str,
+        email: &EmailAddr,
+    ) -> Result<Self, PolicyViolations> {
+        let password = Self::new(password);
+        policy.check(password.as_str(), username, email)?;
+        Ok(password)
+    }
+}
+
+impl crate::args::create::CreateUser {
+    /// Checks the password of the new user against `policy`.
+    pub fn check_password(&self, policy: &PasswordPolicy) -> Result<(), PolicyViolations> {
+        policy.check(self.password.as_str(), &self.username, &self.email)
+    }
+}
+
+#[test]
+fn password_policy() {
+    let email: EmailAddr = "alex@example.com".parse().unwrap();
+    let policy = PasswordPolicy {
+        require_uppercase: true,
+        require_digit: true,
+        ..Default::default()
+    }
+    .with_banned(["Password1"]);
+
+    assert!(PlainPassword::with_policy("Correct horse 9", &policy, "alex", &email).is_ok());
+    assert_eq!(
+        policy.check("alex", "alex", &email),
+        Err(PolicyViolations(vec![
+            PolicyViolation::TooShort { min_length: 8 },
+            PolicyViolation::MissingUppercase,
+            PolicyViolation::MissingDigit,
+            PolicyViolation::SameAsUsername,
+            PolicyViolation::SameAsEmail,
+        ]))
+    );
+    assert_eq!(
+        policy.check("PASSWORD1", "alex", &email),
+        Err(PolicyViolations(vec![PolicyViolation::Banned]))
+    );
+    assert_eq!(
+        serde_json::to_string(&PolicyViolations(vec![
+            PolicyViolation::TooShort { min_length: 8 },
+            PolicyViolation::Banned
+        ]))
+        .unwrap(),
+        r#"[{"rule":"too_short","min_length":8},{"rule":"banned"}]"#
+    );
+}
@@ -1,4 +1,11 @@
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize, Serialize,
+};
+use std::{fmt::Display, str::FromStr, sync::OnceLock};
+
+static DECODING: OnceLock<Decoding> = OnceLock::new();
 
 bitflags::bitflags! {
     /// The goal here is to define which actions a user of the database can perform.
@@ -8,10 +15,29 @@ bitflags::bitflags! {
     /// *Member*: They are able to update any passes assigned to their account as well as basic
     /// information such as email, username and password.
     ///
-    /// *Admin*: They are able to CRUD the user and pass tables. Additionally they can view financial
-    /// information (note that this table does not yet exist).
+    /// *Admin*: They are able to CRUD the user and pass tables. Additionally they can view sales,
+    /// issue refunds and manage products and prices.
     ///
     /// *Root*: Can do everything
+    ///
+    /// Further roles can be stored in the database as [`Role`](super::role::Role)s, whose
+    /// permissions are added to those of every user they are assigned to.
+    ///
+    /// # Allocating bits
+    ///
+    /// Stored values are raw bits, so adding a flag must never give it to anyone by accident:
+    ///
+    /// - A new flag takes the next free bit. Bits are never renumbered or reused, and the bit of a
+    ///   removed flag stays reserved, since old values may still have it set.
+    /// - Users only hold a new flag once it is granted to them. The exception is `ROOT`, which is
+    ///   stored as all ones and so holds every flag, including future ones.
+    /// - Composites such as `ADMIN` only exist in code. Adding a flag to one does not change stored
+    ///   values; existing holders have to be migrated explicitly.
+    /// - Values with bits outside [`Permissions::KNOWN`] were written by a newer version, see
+    ///   [`Decoding`].
+    ///
+    /// Bits 0-3 are used for users, bits 4-7 for passes and bits 8-10 for finance. The finance
+    /// flags were added to `ADMIN` later, see [`Permissions::migrate_finance`].
     // TODO: Write unit tests for the permissions system
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct Permissions: u32 {
@@ -30,7 +56,12 @@ bitflags::bitflags! {
             | Permissions::USER_UPDATE.bits()
             | Permissions::USER_DELETE.bits();
 
-        const ADMIN = Self::PASS_CRUD.bits() | Self::USER_CRUD.bits();
+        const FINANCE =
+              Permissions::SALES_READ.bits()
+            | Permissions::SALES_REFUND.bits()
+            | Permissions::PRODUCT_MANAGE.bits();
+
+        const ADMIN = Self::PASS_CRUD.bits() | Self::USER_CRUD.bits() | Self::FINANCE.bits();
 
 
         /// Can create users.
@@ -50,9 +81,233 @@ bitflags::bitflags! {
         const PASS_UPDATE      = 1 << 6;
         /// Can delete passes for any user
         const PASS_DELETE      = 1 << 7;
+
+        /// Can view sales and payments
+        const SALES_READ       = 1 << 8;
+        /// Can refund payments
+        const SALES_REFUND     = 1 << 9;
+        /// Can create and edit products and their prices
+        const PRODUCT_MANAGE   = 1 << 10;
+    }
+}
+
+impl Permissions {
+    /// Every bit with a name of its own. All other bits are unknown, except in `ROOT`.
+    pub const KNOWN: Permissions = {
+        let flags = <Self as bitflags::Flags>::FLAGS;
+        let (mut bits, mut i) = (0, 0);
+        while i < flags.len() {
+            if flags[i].value().bits().is_power_of_two() {
+                bits |= flags[i].value().bits();
+            }
+            i += 1;
+        }
+        Permissions::from_bits_retain(bits)
+    };
+
+    /// The bits of `self` which this version does not know. `ROOT` has none.
+    pub fn unknown(self) -> Permissions {
+        match self == Self::ROOT {
+            true => Self::NONE,
+            false => self.difference(Self::KNOWN),
+        }
+    }
+
+    /// Like `from_bits_retain`, but fails if any bit is [`unknown`](Self::unknown).
+    pub fn from_bits_strict(bits: u32) -> Result<Self, UnknownBits> {
+        let permissions = Self::from_bits_retain(bits);
+        match permissions.unknown() {
+            Self::NONE => Ok(permissions),
+            unknown => Err(UnknownBits { unknown }),
+        }
+    }
+
+    /// `ADMIN` before the finance flags were added to it.
+    pub const LEGACY_ADMIN: Permissions =
+        Permissions::from_bits_retain(Self::PASS_CRUD.bits() | Self::USER_CRUD.bits());
+
+    /// Gives values holding the former [`LEGACY_ADMIN`](Self::LEGACY_ADMIN) the finance flags
+    /// which `ADMIN` now includes. Everything else, including `ROOT`, is returned unchanged.
+    ///
+    /// Stored values are not migrated implicitly; this has to be applied once to every stored
+    /// value, e.g. with `Store::migrate_finance_permissions`.
+    pub fn migrate_finance(self) -> Self {
+        match self != Self::ROOT && self.contains(Self::LEGACY_ADMIN) {
+            true => self | Self::FINANCE,
+            false => self,
+        }
+    }
+
+    /// Checks that `self` contains all of `needed`, listing the flags it lacks otherwise.
+    pub fn require(self, needed: Permissions) -> Result<(), MissingPermissions> {
+        let missing = match needed == Self::ROOT && self != Self::ROOT {
+            true => Self::ROOT,
+            false => needed.difference(self),
+        };
+        match missing {
+            Self::NONE => Ok(()),
+            missing => Err(MissingPermissions { missing }),
+        }
+    }
+
+    /// Decodes stored bits according to the installed [`Decoding`].
+    pub fn decode(bits: u32) -> Result<Self, UnknownBits> {
+        Decoding::global().decode(bits)
+    }
+
+    /// The individual named flags set in `self`, leaving out composites like `ADMIN`.
+    pub fn flags(self) -> impl Iterator<Item = Permissions> {
+        <Self as bitflags::Flags>::FLAGS
+            .iter()
+            .map(|flag| *flag.value())
+            .filter(move |flag| flag.bits().is_power_of_two() && self.contains(*flag))
+    }
+}
+
+/// The flags lacking for an action, see [`Permissions::require`]. Serializes as
+/// `{"missing": ["USER_READ", "PASS_DELETE"]}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissingPermissions {
+    #[serde(serialize_with = "serialize_missing")]
+    pub missing: Permissions,
+}
+
+impl MissingPermissions {
+    /// The names of the missing flags. Missing `ROOT` is reported as such rather than as the
+    /// flags root has beyond the actor, and bits without a name are given in hex.
+    pub fn names(&self) -> Vec<String> {
+        if self.missing == Permissions::ROOT {
+            return vec!["ROOT".into()];
+        }
+        let mut names: Vec<String> = self.missing.flags().map(|flag| flag.to_string()).collect();
+        let unknown = self.missing.difference(Permissions::KNOWN);
+        if !unknown.is_empty() {
+            names.push(format!("{:#x}", unknown.bits()));
+        }
+        names
+    }
+}
+
+fn serialize_missing<S>(missing: &Permissions, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    MissingPermissions { missing: *missing }
+        .names()
+        .serialize(serializer)
+}
+
+impl Display for MissingPermissions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "missing permissions: {}", self.names().join(", "))
+    }
+}
+
+impl std::error::Error for MissingPermissions {}
+
+/// Permission bits which this version has no name for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownBits {
+    pub unknown: Permissions,
+}
+
+impl Display for UnknownBits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown permission bits {:#x}", self.unknown.bits())
+    }
+}
+
+impl std::error::Error for UnknownBits {}
+
+/// How stored permission bits are decoded by serde and sqlx.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Decoding {
+    /// Keep unknown bits, so that saving the value again does not lose them. They can be found
+    /// with [`Permissions::unknown`].
+    #[default]
+    Retain,
+    /// Fail with [`UnknownBits`].
+    Strict,
+}
+
+impl Decoding {
+    /// Makes `self` the mode used when decoding. This can only be done once and should happen at
+    /// startup; it fails with the installed mode otherwise.
+    pub fn install(self) -> Result<(), Decoding> {
+        DECODING.set(self).map_err(|_| *Decoding::global())
+    }
+
+    /// The installed mode, or [`Decoding::Retain`] if none was installed.
+    pub fn global() -> &'static Decoding {
+        DECODING.get_or_init(Decoding::default)
+    }
+
+    /// Decodes `bits` in this mode.
+    pub fn decode(self, bits: u32) -> Result<Permissions, UnknownBits> {
+        match self {
+            Decoding::Retain => Ok(Permissions::from_bits_retain(bits)),
+            Decoding::Strict => Permissions::from_bits_strict(bits),
+        }
+    }
+
+    /// Parses the format written by [`Display`] and decodes the result in this mode, so that hex
+    /// bits in the string are checked like stored ones.
+    pub fn parse(self, s: &str) -> Result<Permissions, PermissionsParseError> {
+        let parsed: Permissions =
+            bitflags::parser::from_str(s).map_err(PermissionsParseError::Invalid)?;
+        self.decode(parsed.bits())
+            .map_err(PermissionsParseError::Unknown)
+    }
+}
+
+#[derive(Debug)]
+pub enum PermissionsParseError {
+    /// The string is not a list of flag names and hex bits separated by `|`.
+    Invalid(bitflags::parser::ParseError),
+    /// The string holds bits which the installed [`Decoding`] does not accept.
+    Unknown(UnknownBits),
+}
+
+impl Display for PermissionsParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PermissionsParseError::Invalid(e) => e.fmt(f),
+            PermissionsParseError::Unknown(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PermissionsParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PermissionsParseError::Invalid(_) => None,
+            PermissionsParseError::Unknown(e) => Some(e),
+        }
     }
 }
 
+impl Display for Permissions {
+    /// Writes the flag names separated by `|`, e.g. `USER_READ | PASS_CRUD`, preferring composites
+    /// where they apply. Bits without a name are written in hex.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.is_empty() {
+            true => f.write_str("NONE"),
+            false => bitflags::parser::to_writer(self, f),
+        }
+    }
+}
+
+impl FromStr for Permissions {
+    type Err = PermissionsParseError;
+
+    /// Parses the format written by [`Display`], e.g. `USER_READ | PASS_CREATE` or `0x10000`.
+    /// Hex bits are checked according to the installed [`Decoding`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Decoding::global().parse(s)
+    }
+}
+
+/// Serializes as the raw bits, which is what is stored. See [`names`] for a readable form.
 impl Serialize for Permissions {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -62,11 +317,245 @@ impl Serialize for Permissions {
     }
 }
 
+/// Human readable formats accept the raw bits, a string like `"USER_READ | PASS_CREATE"`, or a
+/// list of names like `["USER_READ", "PASS_CRUD"]`. Binary formats only hold the raw bits as a
+/// `u32`. Bits are checked according to the installed [`Decoding`], however they are written.
 impl<'de> Deserialize<'de> for Permissions {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        <u32 as Deserialize>::deserialize(deserializer).map(Permissions::from_bits_truncate)
+        let visitor = PermissionsVisitor(*Decoding::global());
+        match deserializer.is_human_readable() {
+            true => deserializer.deserialize_any(visitor),
+            false => deserializer.deserialize_u32(visitor),
+        }
+    }
+}
+
+struct PermissionsVisitor(Decoding);
+
+impl<'de> Visitor<'de> for PermissionsVisitor {
+    type Value = Permissions;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("permission bits, a string of flag names or a list of flag names")
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let bits = u32::try_from(v)
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))?;
+        self.0.decode(bits).map_err(E::custom)
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        u64::try_from(v)
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+            .and_then(|v| self.visit_u64(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.0.parse(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut permissions = Permissions::NONE;
+        while let Some(name) = seq.next_element::<String>()? {
+            permissions |= self.0.parse(&name).map_err(serde::de::Error::custom)?;
+        }
+        Ok(permissions)
+    }
+}
+
+/// Serializes [`Permissions`] as a list of flag names, e.g. `["USER_READ", "PASS_CRUD"]`, for use
+/// with `#[serde(with = "krag_types::user::permissions::names")]`.
+pub mod names {
+    use super::*;
+
+    pub fn serialize<S>(permissions: &Permissions, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut names = permissions.iter_names();
+        let mut seq = serializer.serialize_seq(None)?;
+        for (name, _) in names.by_ref() {
+            seq.serialize_element(name)?;
+        }
+        let unknown = names.remaining().bits();
+        if unknown != 0 {
+            seq.serialize_element(&format!("{unknown:#x}"))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Permissions, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Permissions::deserialize(deserializer)
+    }
+}
+
+#[test]
+fn flags() {
+    assert_eq!(
+        Permissions::USER_CRUD.flags().collect::<Vec<_>>(),
+        [
+            Permissions::USER_CREATE,
+            Permissions::USER_READ,
+            Permissions::USER_UPDATE,
+            Permissions::USER_DELETE
+        ]
+    );
+    assert_eq!(Permissions::ROOT.flags().count(), 11);
+    assert_eq!(Permissions::NONE.flags().count(), 0);
+}
+
+#[test]
+fn readable_permissions() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Config {
+        #[serde(with = "names")]
+        permissions: Permissions,
     }
+
+    let permissions = Permissions::USER_READ | Permissions::PASS_CRUD;
+    assert_eq!(permissions.to_string(), "PASS_CRUD | USER_READ");
+    assert_eq!(Permissions::NONE.to_string(), "NONE");
+    assert_eq!(Permissions::ROOT.to_string(), "ROOT");
+    assert_eq!(
+        "USER_READ | PASS_CRUD".parse::<Permissions>().unwrap(),
+        permissions
+    );
+    assert!("USER_READ | NOPE".parse::<Permissions>().is_err());
+
+    let config = Config { permissions };
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(json, r#"{"permissions":["PASS_CRUD","USER_READ"]}"#);
+    assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+
+    for json in [
+        "242",
+        r#""USER_READ | PASS_CRUD""#,
+        r#"["USER_READ","PASS_CRUD"]"#,
+    ] {
+        assert_eq!(
+            serde_json::from_str::<Permissions>(json).unwrap(),
+            permissions
+        );
+    }
+    assert_eq!(serde_json::to_string(&permissions).unwrap(), "242");
+
+    let unnamed = Config {
+        permissions: Permissions::from_bits_retain(0x10002),
+    };
+    let json = serde_json::to_string(&unnamed).unwrap();
+    assert_eq!(json, r#"{"permissions":["USER_READ","0x10000"]}"#);
+    assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), unnamed);
+}
+
+#[test]
+fn unknown_bits() {
+    assert_eq!(Permissions::KNOWN.bits(), 0x7ff);
+    assert_eq!(Permissions::ROOT.unknown(), Permissions::NONE);
+    assert_eq!(Permissions::ADMIN.unknown(), Permissions::NONE);
+    assert_eq!(
+        Permissions::from_bits_retain(0x10002).unknown().bits(),
+        0x10000
+    );
+
+    assert_eq!(Permissions::from_bits_strict(!0), Ok(Permissions::ROOT));
+    assert_eq!(
+        Permissions::from_bits_strict(0x10002),
+        Err(UnknownBits {
+            unknown: Permissions::from_bits_retain(0x10000)
+        })
+    );
+    let permissions: Permissions = serde_json::from_str("65538").unwrap();
+    assert_eq!(permissions.bits(), 0x10002);
+    assert!(!permissions.contains(Permissions::ROOT));
+    let binary = bincode::serialize(&(permissions, Permissions::ADMIN)).unwrap();
+    assert_eq!(
+        bincode::deserialize::<(Permissions, Permissions)>(&binary).unwrap(),
+        (permissions, Permissions::ADMIN)
+    );
+    assert_eq!(
+        "USER_READ | 0x10000".parse::<Permissions>().unwrap().bits(),
+        0x10002
+    );
+
+    // Strict decoding can not be installed here without affecting the other tests, so the
+    // visitor is driven directly.
+    let strict = |json: &str| {
+        serde::Deserializer::deserialize_any(
+            &mut serde_json::Deserializer::from_str(json),
+            PermissionsVisitor(Decoding::Strict),
+        )
+    };
+    assert!(strict("65538").is_err());
+    assert!(strict(r#""USER_READ | 0x10000""#).is_err());
+    assert!(strict(r#"["USER_READ", "0x10000"]"#).is_err());
+    assert_eq!(strict(r#""ROOT""#).unwrap(), Permissions::ROOT);
+    assert!(matches!(
+        Decoding::Strict.parse("0x10002"),
+        Err(PermissionsParseError::Unknown(_))
+    ));
+}
+
+#[test]
+fn require() {
+    let admin = Permissions::ADMIN;
+    assert_eq!(admin.require(Permissions::USER_READ), Ok(()));
+    assert_eq!(Permissions::ROOT.require(Permissions::ROOT), Ok(()));
+
+    let missing = Permissions::USER_READ
+        .require(Permissions::USER_CRUD)
+        .unwrap_err();
+    assert_eq!(
+        missing.names(),
+        ["USER_CREATE", "USER_UPDATE", "USER_DELETE"]
+    );
+    assert_eq!(
+        missing.to_string(),
+        "missing permissions: USER_CREATE, USER_UPDATE, USER_DELETE"
+    );
+    let json = serde_json::to_string(&missing).unwrap();
+    assert_eq!(
+        json,
+        r#"{"missing":["USER_CREATE","USER_UPDATE","USER_DELETE"]}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<MissingPermissions>(&json).unwrap(),
+        missing
+    );
+
+    let missing = admin.require(Permissions::ROOT).unwrap_err();
+    assert_eq!(missing.names(), ["ROOT"]);
+}
+
+#[test]
+fn migrate_finance() {
+    assert_eq!(Permissions::LEGACY_ADMIN.bits(), 0xff);
+    assert_eq!(
+        Permissions::LEGACY_ADMIN.migrate_finance(),
+        Permissions::ADMIN
+    );
+    assert_eq!(Permissions::ADMIN.migrate_finance(), Permissions::ADMIN);
+    assert_eq!(Permissions::ROOT.migrate_finance(), Permissions::ROOT);
+    assert_eq!(
+        Permissions::USER_CRUD.migrate_finance(),
+        Permissions::USER_CRUD
+    );
 }
@@ -0,0 +1,136 @@
+use super::{password::PlainPassword, User, UserId};
+use crate::token::{Token, TokenHash};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::table::{BindValues, Queryable},
+    backend_proc_macro::BindValues,
+};
+
+pub type PasswordResetId = i64;
+
+/// A request to reset the password of a user. The [`Token`] is sent to the user and only its hash
+/// is kept here.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct PasswordReset {
+    pub id: PasswordResetId,
+    pub user_id: UserId,
+    pub token_hash: TokenHash,
+    pub expiry: DateTime<Utc>,
+    pub used: bool,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for PasswordReset {
+    type CreateArgs = crate::args::create::CreatePasswordReset;
+    type QueryArgs = crate::args::query::QueryPasswordReset;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResetError {
+    /// The token does not match, or belongs to another user.
+    InvalidToken,
+    Expired,
+    AlreadyUsed,
+}
+
+impl Display for ResetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResetError::InvalidToken => f.write_str("the reset token is invalid"),
+            ResetError::Expired => f.write_str("the reset token has expired"),
+            ResetError::AlreadyUsed => f.write_str("the reset token has already been used"),
+        }
+    }
+}
+
+impl std::error::Error for ResetError {}
+
+impl PasswordReset {
+    /// Issues a reset token for `user_id` which is valid for `ttl`. The token has to be sent to
+    /// the user and the returned args stored.
+    pub fn issue(
+        user_id: UserId,
+        ttl: Duration,
+    ) -> (Token, crate::args::create::CreatePasswordReset) {
+        let token = Token::generate();
+        let reset = crate::args::create::CreatePasswordReset {
+            id: None,
+            user_id,
+            token_hash: token.hash(),
+            expiry: Utc::now() + ttl,
+            used: false,
+        };
+        (token, reset)
+    }
+
+    /// Checks that `token` redeems this reset.
+    pub fn verify(&self, token: &str) -> Result<(), ResetError> {
+        if !self.token_hash.matches(token) {
+            Err(ResetError::InvalidToken)
+        } else if self.used {
+            Err(ResetError::AlreadyUsed)
+        } else if self.expiry <= Utc::now() {
+            Err(ResetError::Expired)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Redeems `token`, marking the reset as used and giving `user` the new password. Both have to
+    /// be persisted afterwards.
+    ///
+    /// Because the password hash is the user's `AuthUser::session_auth_hash`, every existing
+    /// session of the user is invalidated by the change.
+    pub fn consume(
+        &mut self,
+        token: &str,
+        user: &mut User,
+        new_password: &PlainPassword,
+    ) -> Result<(), ResetError> {
+        if user.id != self.user_id {
+            return Err(ResetError::InvalidToken);
+        }
+        self.verify(token)?;
+        self.used = true;
+        user.password = new_password.hash();
+        Ok(())
+    }
+}
+
+#[test]
+fn password_reset() {
+    let mut user = User {
+        id: 3,
+        ..Default::default()
+    };
+    let (token, args) = PasswordReset::issue(user.id, Duration::minutes(30));
+    let mut reset = PasswordReset {
+        id: 1,
+        user_id: args.user_id,
+        token_hash: args.token_hash,
+        expiry: args.expiry,
+        used: args.used,
+    };
+    let new_password = PlainPassword::new("correct horse");
+
+    assert_eq!(reset.verify("guess"), Err(ResetError::InvalidToken));
+    assert_eq!(
+        reset.consume(token.as_str(), &mut User::default(), &new_password),
+        Err(ResetError::InvalidToken)
+    );
+    assert_eq!(
+        reset.consume(token.as_str(), &mut user, &new_password),
+        Ok(())
+    );
+    assert!(user.password.verify(&new_password).is_ok());
+    assert_eq!(reset.verify(token.as_str()), Err(ResetError::AlreadyUsed));
+
+    reset.used = false;
+    reset.expiry = Utc::now() - Duration::minutes(1);
+    assert_eq!(reset.verify(token.as_str()), Err(ResetError::Expired));
+}
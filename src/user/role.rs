@@ -0,0 +1,213 @@
+use super::{permissions::Permissions, User, UserId};
+use crate::{
+    args::{create::CreateRole, query::QueryRole},
+    authorize::{authorize_grant, Actor, Escalation},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::table::{BindValues, Queryable},
+    backend_proc_macro::BindValues,
+};
+
+pub type RoleId = i64;
+
+/// A named set of permissions, e.g. "Front desk", which can be assigned to many users at once.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct Role {
+    pub id: RoleId,
+    pub name: String,
+    pub permissions: Permissions,
+    pub description: String,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for Role {
+    type CreateArgs = crate::args::create::CreateRole;
+    type QueryArgs = crate::args::query::QueryRole;
+}
+
+/// Assigns a [`Role`] to a user.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct UserRole {
+    pub user_id: UserId,
+    pub role_id: RoleId,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for UserRole {
+    type CreateArgs = crate::args::create::CreateUserRole;
+    type QueryArgs = crate::args::query::QueryUserRole;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoleError {
+    /// The actor lacks the permissions to manage roles.
+    Forbidden,
+    /// The role would hand out permissions the actor does not hold.
+    Escalation(Escalation),
+}
+
+impl Display for RoleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoleError::Forbidden => f.write_str("not allowed to manage roles"),
+            RoleError::Escalation(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RoleError {}
+
+impl Role {
+    /// The permissions needed to create, edit and assign roles. This is what `ADMIN` was before
+    /// the finance flags, and deliberately does not follow it.
+    pub const MANAGE: Permissions = Permissions::from_bits_retain(
+        Permissions::USER_CRUD.bits() | Permissions::PASS_CRUD.bits(),
+    );
+
+    /// Checks that `actor` may manage roles and holds every permission in `granted` without any
+    /// temporary grant.
+    fn check_actor(actor: Actor, granted: Permissions) -> Result<(), RoleError> {
+        if !actor.permissions.contains(Self::MANAGE) {
+            return Err(RoleError::Forbidden);
+        }
+        match granted.difference(actor.base()) {
+            Permissions::NONE => Ok(()),
+            missing => Err(RoleError::Escalation(Escalation::NotHeld { missing })),
+        }
+    }
+
+    /// Checks that `actor` may create the role described by `args`, returning the args to store.
+    /// The actor has to hold the role's permissions.
+    pub fn create(actor: Actor, args: CreateRole) -> Result<CreateRole, RoleError> {
+        Self::check_actor(actor, args.permissions)?;
+        Ok(args)
+    }
+
+    /// Applies the fields set in `changes` to the role. The id can not be changed, and the actor has
+    /// to hold any permissions added. The role has to be persisted afterwards.
+    pub fn edit(&mut self, actor: Actor, changes: QueryRole) -> Result<(), RoleError> {
+        let added = changes
+            .permissions
+            .map_or(Permissions::NONE, |new| new.difference(self.permissions));
+        Self::check_actor(actor, added)?;
+        if let Some(name) = changes.name {
+            self.name = name;
+        }
+        if let Some(permissions) = changes.permissions {
+            self.permissions = permissions;
+        }
+        if let Some(description) = changes.description {
+            self.description = description;
+        }
+        Ok(())
+    }
+}
+
+impl UserRole {
+    /// Checks that `actor` may give `role` to `user_id`, returning the assignment to store. The
+    /// actor has to hold the role's permissions, and only root may assign roles to themselves.
+    pub fn assign(
+        actor: Actor,
+        user_id: UserId,
+        role: &Role,
+    ) -> Result<crate::args::create::CreateUserRole, RoleError> {
+        Role::check_actor(actor, role.permissions)?;
+        authorize_grant(actor, user_id, Permissions::NONE, role.permissions)
+            .map_err(RoleError::Escalation)?;
+        Ok(crate::args::create::CreateUserRole {
+            user_id,
+            role_id: role.id,
+        })
+    }
+}
+
+impl User {
+    /// The union of the user's own permissions and those of `roles`, which should be the roles
+    /// assigned to the user.
+    pub fn effective_permissions(&self, roles: &[Role]) -> Permissions {
+        roles.iter().fold(self.permissions, |permissions, role| {
+            permissions | role.permissions
+        })
+    }
+}
+
+#[test]
+fn roles() {
+    let actor = |permissions| Actor {
+        id: 1,
+        permissions,
+        granted: Permissions::NONE,
+    };
+    let admin = actor(Permissions::ADMIN);
+    let mut front_desk = Role {
+        id: 1,
+        name: "Front desk".into(),
+        permissions: Permissions::USER_READ,
+        description: "Checks members in".into(),
+    };
+    let user = User {
+        id: 2,
+        permissions: Permissions::PASS_READ,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        user.effective_permissions(&[front_desk.clone()]),
+        Permissions::USER_READ | Permissions::PASS_READ
+    );
+    assert_eq!(user.effective_permissions(&[]), Permissions::PASS_READ);
+
+    let changes = QueryRole {
+        permissions: Some(Permissions::USER_READ | Permissions::PASS_READ),
+        ..Default::default()
+    };
+    assert_eq!(
+        front_desk.edit(actor(Permissions::USER_CRUD), changes.clone()),
+        Err(RoleError::Forbidden)
+    );
+    assert_eq!(front_desk.edit(admin, changes), Ok(()));
+    assert_eq!(front_desk.name, "Front desk");
+    assert_eq!(
+        front_desk.permissions,
+        Permissions::USER_READ | Permissions::PASS_READ
+    );
+
+    assert!(UserRole::assign(actor(Permissions::NONE), user.id, &front_desk).is_err());
+    assert!(UserRole::assign(admin, user.id, &front_desk).is_ok());
+    assert!(matches!(
+        UserRole::assign(admin, admin.id, &front_desk),
+        Err(RoleError::Escalation(Escalation::OwnPermissions { id: 1 }))
+    ));
+    let granted = Actor {
+        granted: Permissions::USER_READ,
+        ..admin
+    };
+    assert!(matches!(
+        UserRole::assign(granted, user.id, &front_desk),
+        Err(RoleError::Escalation(Escalation::NotHeld { .. }))
+    ));
+
+    let escalating = QueryRole {
+        permissions: Some(Permissions::ROOT),
+        ..Default::default()
+    };
+    assert!(matches!(
+        front_desk.edit(admin, escalating),
+        Err(RoleError::Escalation(Escalation::NotHeld { .. }))
+    ));
+    assert!(front_desk
+        .edit(
+            actor(Permissions::ROOT),
+            QueryRole {
+                permissions: Some(Permissions::ROOT),
+                ..Default::default()
+            }
+        )
+        .is_ok());
+}
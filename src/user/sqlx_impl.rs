@@ -1,6 +1,7 @@
 #![cfg(feature = "sqlite")]
 use super::password::PasswordHash;
 use super::permissions::Permissions;
+use super::two_factor::TotpSecret;
 use sqlx::{Decode, Encode, Sqlite, Type};
 
 impl Type<Sqlite> for Permissions {
@@ -23,7 +24,7 @@ impl<'r> Decode<'r, Sqlite> for Permissions {
         value: <Sqlite as sqlx::Database>::ValueRef<'r>,
     ) -> Result<Self, sqlx::error::BoxDynError> {
         let val = <u32 as Decode<Sqlite>>::decode(value)?;
-        Ok(Self::from_bits_truncate(val))
+        Ok(Self::decode(val)?)
     }
 }
 
@@ -36,6 +37,7 @@ impl<'r> Encode<'r, Sqlite> for PasswordHash {
     }
 }
 
+/// Hashes read from the database were validated when they were stored and are not parsed again.
 impl<'r> Decode<'r, Sqlite> for PasswordHash {
     fn decode(
         value: <Sqlite as sqlx::Database>::ValueRef<'r>,
@@ -50,3 +52,26 @@ impl Type<Sqlite> for PasswordHash {
         <str as Type<Sqlite>>::type_info()
     }
 }
+
+impl<'r> Encode<'r, Sqlite> for TotpSecret {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'r>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        <Vec<u8> as Encode<Sqlite>>::encode_by_ref(&self.0, buf)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for TotpSecret {
+    fn decode(
+        value: <Sqlite as sqlx::Database>::ValueRef<'r>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        Ok(Self(<Vec<u8> as Decode<Sqlite>>::decode(value)?))
+    }
+}
+
+impl Type<Sqlite> for TotpSecret {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <[u8] as Type<Sqlite>>::type_info()
+    }
+}
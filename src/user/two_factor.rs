@@ -0,0 +1,453 @@
+use super::{permissions::Permissions, User, UserId};
+use crate::token::{Token, TokenHash};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use password_hash::rand_core::{OsRng, RngCore};
+use serde::{ser::SerializeStruct, Deserialize, Serialize};
+use sha1::Sha1;
+use std::fmt::Display;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::table::{BindValues, Queryable},
+    backend_proc_macro::BindValues,
+};
+
+/// Number of digits in a code.
+pub const DIGITS: u32 = 6;
+/// Seconds for which a code is valid.
+pub const PERIOD: i64 = 30;
+/// Number of periods a code may be early or late to allow for clock drift.
+pub const SKEW: i64 = 1;
+/// Random bytes in a recovery code, 128 bits so the codes can not be brute forced from their hash.
+pub const RECOVERY_CODE_BYTES: usize = 16;
+
+/// The shared secret of a TOTP authenticator (RFC 6238, HMAC-SHA1). Unlike passwords the secret
+/// has to be stored in the clear, since the server needs it to compute the expected codes. It is
+/// (de)serialized as unpadded base32, the form authenticator apps expect.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TotpSecret(pub(crate) Vec<u8>);
+
+impl TotpSecret {
+    /// Generates a secret from 20 random bytes, as recommended by RFC 4226.
+    pub fn generate() -> Self {
+        let mut bytes = vec![0; 20];
+        OsRng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+
+    pub fn from_base32(secret: &str) -> Option<Self> {
+        let secret = secret.trim_end_matches('=').to_ascii_uppercase();
+        data_encoding::BASE32_NOPAD
+            .decode(secret.as_bytes())
+            .ok()
+            .map(Self)
+    }
+
+    pub fn to_base32(&self) -> String {
+        data_encoding::BASE32_NOPAD.encode(&self.0)
+    }
+
+    /// The `otpauth://` URI to enrol this secret in an authenticator app, usually shown as a QR
+    /// code.
+    pub fn otpauth_uri(&self, issuer: &str, account: &str) -> String {
+        let issuer = percent_encode(issuer);
+        format!(
+            "otpauth://totp/{issuer}:{}?secret={}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={PERIOD}",
+            percent_encode(account),
+            self.to_base32(),
+        )
+    }
+
+    /// The code for the period containing `time`.
+    pub fn code_at(&self, time: DateTime<Utc>) -> String {
+        self.code_for_step(time.timestamp().div_euclid(PERIOD))
+    }
+
+    fn code_for_step(&self, step: i64) -> String {
+        let mut mac =
+            Hmac::<Sha1>::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        mac.update(&step.to_be_bytes());
+        let digest = mac.finalize().into_bytes();
+        let offset = (digest[19] & 0xf) as usize;
+        let value = u32::from_be_bytes(digest[offset..offset + 4].try_into().unwrap());
+        format!(
+            "{:0width$}",
+            (value & 0x7fff_ffff) % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        )
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+impl Drop for TotpSecret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TotpSecret([redacted])")
+    }
+}
+
+impl Serialize for TotpSecret {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_base32())
+    }
+}
+
+impl<'de> Deserialize<'de> for TotpSecret {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let secret = String::deserialize(deserializer)?;
+        Self::from_base32(&secret).ok_or_else(|| serde::de::Error::custom("invalid base32 secret"))
+    }
+}
+
+/// The TOTP authenticator of a user. A user has at most one, and it only counts once `enabled`,
+/// i.e. after the user proved that enrolment worked by entering a code, see
+/// [`TwoFactor::confirm`].
+#[derive(Clone, PartialEq, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct TwoFactor {
+    pub user_id: UserId,
+    pub secret: TotpSecret,
+    pub enabled: bool,
+    /// The time step of the last accepted code, so that a code can not be replayed.
+    pub last_step: Option<i64>,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for TwoFactor {
+    type CreateArgs = crate::args::create::CreateTwoFactor;
+    type QueryArgs = crate::args::query::QueryTwoFactor;
+}
+
+impl Serialize for TwoFactor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("TwoFactor", 4)?;
+        state.serialize_field("user_id", &self.user_id)?;
+        state.serialize_field("secret", &"[REDACTED]")?;
+        state.serialize_field("enabled", &self.enabled)?;
+        state.serialize_field("last_step", &self.last_step)?;
+        state.end()
+    }
+}
+
+impl std::fmt::Debug for TwoFactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TwoFactor")
+            .field("user_id", &self.user_id)
+            .field("secret", &"[redacted]")
+            .field("enabled", &self.enabled)
+            .field("last_step", &self.last_step)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TwoFactorError {
+    InvalidCode,
+    /// The code has already been used to log in.
+    CodeReused,
+    AlreadyUsed,
+    /// The user holds permissions for which the [`TwoFactorPolicy`] requires two-factor
+    /// authentication, but has not enabled it.
+    Required,
+}
+
+impl Display for TwoFactorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TwoFactorError::InvalidCode => f.write_str("the code is invalid"),
+            TwoFactorError::CodeReused => f.write_str("the code has already been used"),
+            TwoFactorError::AlreadyUsed => f.write_str("the recovery code has already been used"),
+            TwoFactorError::Required => {
+                f.write_str("two-factor authentication is required for this account")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TwoFactorError {}
+
+impl TwoFactor {
+    /// Starts enrolment for `user_id` with a fresh secret. The returned args have to be stored and
+    /// the secret shown to the user, e.g. with [`TotpSecret::otpauth_uri`].
+    pub fn enrol(user_id: UserId) -> crate::args::create::CreateTwoFactor {
+        crate::args::create::CreateTwoFactor {
+            user_id,
+            secret: TotpSecret::generate(),
+            enabled: false,
+            last_step: None,
+        }
+    }
+
+    /// Checks `code` against the current time, allowing [`SKEW`] periods of drift. The accepted
+    /// step is remembered, so `self` has to be persisted afterwards.
+    pub fn verify(&mut self, code: &str) -> Result<(), TwoFactorError> {
+        self.verify_at(code, Utc::now())
+    }
+
+    pub fn verify_at(&mut self, code: &str, now: DateTime<Utc>) -> Result<(), TwoFactorError> {
+        let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+        let now = now.timestamp().div_euclid(PERIOD);
+        let step = (now - SKEW..=now + SKEW)
+            .find(|&step| {
+                self.secret
+                    .code_for_step(step)
+                    .as_bytes()
+                    .ct_eq(code.as_bytes())
+                    .into()
+            })
+            .ok_or(TwoFactorError::InvalidCode)?;
+
+        if self.last_step.is_some_and(|last| step <= last) {
+            return Err(TwoFactorError::CodeReused);
+        }
+        self.last_step = Some(step);
+        Ok(())
+    }
+
+    /// Finishes enrolment by checking a code from the user's authenticator.
+    pub fn confirm(&mut self, code: &str) -> Result<(), TwoFactorError> {
+        self.verify(code)?;
+        self.enabled = true;
+        Ok(())
+    }
+}
+
+pub type RecoveryCodeId = i64;
+
+/// A single use code which can be entered instead of a TOTP code, e.g. after losing the
+/// authenticator. Only its hash is stored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct RecoveryCode {
+    pub id: RecoveryCodeId,
+    pub user_id: UserId,
+    pub code_hash: TokenHash,
+    pub used: bool,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for RecoveryCode {
+    type CreateArgs = crate::args::create::CreateRecoveryCode;
+    type QueryArgs = crate::args::query::QueryRecoveryCode;
+}
+
+impl RecoveryCode {
+    /// Generates `count` codes of the form `xxxxxxxx-xxxxxxxx-xxxxxxxx-xxxxxxxx` for `user_id`.
+    /// The codes are shown to the user once and the returned args stored; previous codes of the
+    /// user should be deleted.
+    pub fn generate(
+        user_id: UserId,
+        count: usize,
+    ) -> Vec<(Token, crate::args::create::CreateRecoveryCode)> {
+        (0..count)
+            .map(|_| {
+                let mut bytes = [0u8; RECOVERY_CODE_BYTES];
+                OsRng.fill_bytes(&mut bytes);
+                let mut hex = hex::encode(bytes);
+                bytes.zeroize();
+                let code = Token::from(
+                    hex.as_bytes()
+                        .chunks(8)
+                        .map(|group| std::str::from_utf8(group).unwrap())
+                        .collect::<Vec<_>>()
+                        .join("-"),
+                );
+                hex.zeroize();
+                let args = crate::args::create::CreateRecoveryCode {
+                    id: None,
+                    user_id,
+                    code_hash: Self::normalize(code.as_str()).hash(),
+                    used: false,
+                };
+                (code, args)
+            })
+            .collect()
+    }
+
+    fn normalize(code: &str) -> Token {
+        Token::from(
+            code.chars()
+                .filter(char::is_ascii_alphanumeric)
+                .map(|c| c.to_ascii_lowercase())
+                .collect::<String>(),
+        )
+    }
+
+    /// Redeems `code`, marking it as used. It has to be persisted afterwards.
+    pub fn redeem(&mut self, code: &str) -> Result<(), TwoFactorError> {
+        if !self.code_hash.matches(Self::normalize(code).as_str()) {
+            Err(TwoFactorError::InvalidCode)
+        } else if self.used {
+            Err(TwoFactorError::AlreadyUsed)
+        } else {
+            self.used = true;
+            Ok(())
+        }
+    }
+}
+
+/// Which users have to use two-factor authentication.
+///
+/// Nobody is required to by default. Turning the requirement on locks out everyone it covers who
+/// has not enabled a [`TwoFactor`] yet, so they should enrol before it is switched on.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TwoFactorPolicy {
+    /// Users holding any of these permissions need an enabled [`TwoFactor`].
+    pub required_for: Permissions,
+}
+
+impl Default for TwoFactorPolicy {
+    fn default() -> Self {
+        Self {
+            required_for: Permissions::NONE,
+        }
+    }
+}
+
+impl TwoFactorPolicy {
+    /// Requires two-factor authentication from anyone holding an admin permission.
+    pub fn admins() -> Self {
+        Self {
+            required_for: Permissions::ADMIN,
+        }
+    }
+
+    /// Whether someone with the `effective` permissions, including those from roles and
+    /// grants, has to use two-factor authentication.
+    pub fn is_required(&self, effective: Permissions) -> bool {
+        effective.intersects(self.required_for)
+    }
+
+    /// Checks that `user`, holding the `effective` permissions, has enabled two-factor
+    /// authentication if they have to.
+    pub fn check(
+        &self,
+        user: &User,
+        effective: Permissions,
+        two_factor: Option<&TwoFactor>,
+    ) -> Result<(), TwoFactorError> {
+        let enabled = two_factor.is_some_and(|t| t.user_id == user.id && t.enabled);
+        match self.is_required(effective) && !enabled {
+            true => Err(TwoFactorError::Required),
+            false => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn totp() {
+    use chrono::TimeZone;
+
+    // Test vectors from RFC 6238, truncated to 6 digits.
+    let secret = TotpSecret(b"12345678901234567890".to_vec());
+    let at = |t| Utc.timestamp_opt(t, 0).unwrap();
+    assert_eq!(secret.code_at(at(59)), "287082");
+    assert_eq!(secret.code_at(at(1111111109)), "081804");
+    assert_eq!(secret.code_at(at(2000000000)), "279037");
+    assert_eq!(
+        TotpSecret::from_base32(&secret.to_base32()).as_ref(),
+        Some(&secret)
+    );
+    assert_eq!(
+        secret.otpauth_uri("Krag Gym", "jo@example.com"),
+        "otpauth://totp/Krag%20Gym:jo@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+         &issuer=Krag%20Gym&algorithm=SHA1&digits=6&period=30"
+    );
+
+    let mut two_factor = TwoFactor {
+        user_id: 1,
+        secret,
+        enabled: true,
+        last_step: None,
+    };
+    let now = at(1111111109);
+    assert_eq!(
+        two_factor.verify_at("000000", now),
+        Err(TwoFactorError::InvalidCode)
+    );
+    assert_eq!(
+        two_factor.verify_at("081 804", now + chrono::Duration::seconds(30)),
+        Ok(())
+    );
+    assert_eq!(
+        two_factor.verify_at("081804", now),
+        Err(TwoFactorError::CodeReused)
+    );
+    assert!(!format!("{two_factor:?}").contains("GEZDGNBV"));
+
+    let admin = User {
+        id: 1,
+        permissions: Permissions::USER_READ,
+        ..Default::default()
+    };
+    let effective = admin.permissions;
+    assert_eq!(
+        TwoFactorPolicy::default().check(&admin, effective, None),
+        Ok(())
+    );
+    let policy = TwoFactorPolicy::admins();
+    assert_eq!(
+        policy.check(&admin, effective, None),
+        Err(TwoFactorError::Required)
+    );
+    assert_eq!(policy.check(&admin, effective, Some(&two_factor)), Ok(()));
+    let user = User::default();
+    assert_eq!(policy.check(&user, Permissions::NONE, None), Ok(()));
+    assert_eq!(
+        policy.check(&user, Permissions::FINANCE, None),
+        Err(TwoFactorError::Required)
+    );
+}
+
+#[test]
+fn recovery_codes() {
+    let codes = RecoveryCode::generate(1, 10);
+    assert_eq!(codes.len(), 10);
+    let (code, args) = &codes[0];
+    assert_eq!(code.as_str().len(), RECOVERY_CODE_BYTES * 2 + 3);
+    let mut recovery = RecoveryCode {
+        id: 1,
+        user_id: args.user_id,
+        code_hash: args.code_hash.clone(),
+        used: false,
+    };
+    assert_eq!(
+        recovery.redeem("00000000-00000000-00000000-00000000"),
+        Err(TwoFactorError::InvalidCode)
+    );
+    assert_eq!(
+        recovery.redeem(&code.as_str().to_uppercase().replace('-', " ")),
+        Ok(())
+    );
+    assert_eq!(
+        recovery.redeem(code.as_str()),
+        Err(TwoFactorError::AlreadyUsed)
+    );
+}
@@ -0,0 +1,159 @@
+use super::{User, UserId};
+use crate::{
+    email::EmailAddr,
+    token::{Token, TokenHash},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::table::{BindValues, Queryable},
+    backend_proc_macro::BindValues,
+};
+
+pub type EmailVerificationId = i64;
+
+/// A pending confirmation of `email` for a user. The [`Token`] is sent to that address and only its
+/// hash is kept here.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow, BindValues))]
+pub struct EmailVerification {
+    pub id: EmailVerificationId,
+    pub user_id: UserId,
+    pub email: EmailAddr,
+    pub token_hash: TokenHash,
+    pub expiry: DateTime<Utc>,
+}
+
+#[cfg(feature = "sqlite")]
+impl Queryable for EmailVerification {
+    type CreateArgs = crate::args::create::CreateEmailVerification;
+    type QueryArgs = crate::args::query::QueryEmailVerification;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerificationError {
+    /// The token does not match, or belongs to another user.
+    InvalidToken,
+    Expired,
+    /// The user has changed their email since the token was sent.
+    EmailChanged,
+    /// The user has not verified their email but the [`VerificationPolicy`] requires it.
+    Unverified,
+}
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationError::InvalidToken => f.write_str("the verification token is invalid"),
+            VerificationError::Expired => f.write_str("the verification token has expired"),
+            VerificationError::EmailChanged => {
+                f.write_str("the email address has changed since the token was sent")
+            }
+            VerificationError::Unverified => f.write_str("the email address is not verified"),
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+impl EmailVerification {
+    /// Issues a token confirming the current email of `user` which is valid for `ttl`. The token
+    /// has to be sent to that address and the returned args stored.
+    pub fn issue(
+        user: &User,
+        ttl: Duration,
+    ) -> (Token, crate::args::create::CreateEmailVerification) {
+        let token = Token::generate();
+        let verification = crate::args::create::CreateEmailVerification {
+            id: None,
+            user_id: user.id,
+            email: user.email.clone(),
+            token_hash: token.hash(),
+            expiry: Utc::now() + ttl,
+        };
+        (token, verification)
+    }
+
+    /// Redeems `token`, marking the email of `user` as verified. The user has to be persisted
+    /// afterwards.
+    pub fn confirm(&self, token: &str, user: &mut User) -> Result<(), VerificationError> {
+        if user.id != self.user_id || !self.token_hash.matches(token) {
+            Err(VerificationError::InvalidToken)
+        } else if user.email != self.email {
+            Err(VerificationError::EmailChanged)
+        } else if self.expiry <= Utc::now() {
+            Err(VerificationError::Expired)
+        } else {
+            user.email_verified = true;
+            Ok(())
+        }
+    }
+}
+
+/// Which actions require a verified email.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct VerificationPolicy {
+    pub require_for_login: bool,
+    pub require_for_check_in: bool,
+}
+
+impl VerificationPolicy {
+    pub fn check_login(&self, user: &User) -> Result<(), VerificationError> {
+        match self.require_for_login && !user.email_verified {
+            true => Err(VerificationError::Unverified),
+            false => Ok(()),
+        }
+    }
+
+    pub fn check_check_in(&self, user: &User) -> Result<(), VerificationError> {
+        match self.require_for_check_in && !user.email_verified {
+            true => Err(VerificationError::Unverified),
+            false => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn email_verification() {
+    let mut user = User {
+        id: 3,
+        ..Default::default()
+    };
+    let policy = VerificationPolicy {
+        require_for_login: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        policy.check_login(&user),
+        Err(VerificationError::Unverified)
+    );
+    assert_eq!(policy.check_check_in(&user), Ok(()));
+
+    let (token, args) = EmailVerification::issue(&user, Duration::days(1));
+    let verification = EmailVerification {
+        id: 1,
+        user_id: args.user_id,
+        email: args.email,
+        token_hash: args.token_hash,
+        expiry: args.expiry,
+    };
+
+    assert_eq!(
+        verification.confirm("guess", &mut user),
+        Err(VerificationError::InvalidToken)
+    );
+    let mut moved = User {
+        email: "moved@example.com".parse().unwrap(),
+        ..user.clone()
+    };
+    assert_eq!(
+        verification.confirm(token.as_str(), &mut moved),
+        Err(VerificationError::EmailChanged)
+    );
+    assert_eq!(verification.confirm(token.as_str(), &mut user), Ok(()));
+    assert!(user.email_verified);
+    assert_eq!(policy.check_login(&user), Ok(()));
+}
@@ -0,0 +1,161 @@
+//! Renders records for a particular viewer, leaving out the fields they may not see.
+//!
+//! Each field is shown to viewers allowed to perform an action on the record, see [`authorize`].
+//! Reading a user shows their contact details, but only those who may update the user, or the
+//! user themselves, see their permissions. Reading a pass shows all of it. Anyone else, e.g.
+//! another member of a shared pass, only gets the fields needed to tell records apart: a user's
+//! id and username, and a pass's id and owner. Password hashes are always redacted.
+
+use crate::{
+    authorize::{authorize, Action, Actor},
+    pass::UserPass,
+    user::User,
+};
+use serde::{ser::SerializeStruct, Serialize};
+
+/// A record as seen by `viewer`.
+#[derive(Debug, Clone, Copy)]
+pub struct View<'a, T> {
+    pub viewer: Actor,
+    pub record: &'a T,
+}
+
+impl<'a, T> View<'a, T>
+where
+    &'a T: Into<crate::authorize::Resource>,
+{
+    pub fn new(viewer: Actor, record: &'a T) -> Self {
+        Self { viewer, record }
+    }
+
+    /// Views every record in `records` as `viewer`.
+    pub fn all(viewer: Actor, records: &'a [T]) -> Vec<Self> {
+        records
+            .iter()
+            .map(|record| Self { viewer, record })
+            .collect()
+    }
+
+    /// Whether the viewer may perform `action` on the record, and so see the fields it reveals.
+    pub fn allows(&self, action: Action) -> bool {
+        authorize(self.viewer, action, self.record).is_ok()
+    }
+}
+
+impl Serialize for View<'_, User> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let user = self.record;
+        let mut state = serializer.serialize_struct("User", 7)?;
+        state.serialize_field("id", &user.id)?;
+        state.serialize_field("username", &user.username)?;
+        if self.allows(Action::Read) {
+            state.serialize_field("email", &user.email)?;
+            state.serialize_field("email_verified", &user.email_verified)?;
+            state.serialize_field("number", &user.number)?;
+        } else {
+            state.skip_field("email")?;
+            state.skip_field("email_verified")?;
+            state.skip_field("number")?;
+        }
+        if self.allows(Action::Update) {
+            state.serialize_field("permissions", &user.permissions)?;
+        } else {
+            state.skip_field("permissions")?;
+        }
+        state.serialize_field("password", "[REDACTED]")?;
+        state.end()
+    }
+}
+
+impl Serialize for View<'_, UserPass> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let pass = self.record;
+        let mut state = serializer.serialize_struct("UserPass", 5)?;
+        state.serialize_field("id", &pass.id)?;
+        state.serialize_field("user_id", &pass.user_id)?;
+        if self.allows(Action::Read) {
+            state.serialize_field("time_pass", &pass.time_pass)?;
+            state.serialize_field("session_pass", &pass.session_pass)?;
+            state.serialize_field("window", &pass.window)?;
+        } else {
+            state.skip_field("time_pass")?;
+            state.skip_field("session_pass")?;
+            state.skip_field("window")?;
+        }
+        state.end()
+    }
+}
+
+#[test]
+fn redacted_views() {
+    use crate::user::permissions::Permissions;
+    use serde_json::json;
+
+    let users = [
+        User {
+            id: 1,
+            username: "jo".into(),
+            number: Some(5550100),
+            ..Default::default()
+        },
+        User {
+            id: 2,
+            username: "kim".into(),
+            ..Default::default()
+        },
+    ];
+    let jo = Actor::from(&users[0]);
+    let group = serde_json::to_value(View::all(jo, &users)).unwrap();
+    assert_eq!(
+        group,
+        json!([
+            {
+                "id": 1,
+                "username": "jo",
+                "email": "default@email.com",
+                "email_verified": false,
+                "number": 5550100,
+                "permissions": 0,
+                "password": "[REDACTED]"
+            },
+            { "id": 2, "username": "kim", "password": "[REDACTED]" }
+        ])
+    );
+
+    let front_desk = Actor {
+        id: 3,
+        permissions: Permissions::USER_READ,
+        granted: Permissions::NONE,
+    };
+    let view = serde_json::to_value(View::new(front_desk, &users[1])).unwrap();
+    assert_eq!(view["email"], "default@email.com");
+    assert!(view.get("permissions").is_none());
+    assert_eq!(view["password"], "[REDACTED]");
+    let manager = Actor {
+        id: 4,
+        permissions: Permissions::USER_UPDATE,
+        granted: Permissions::NONE,
+    };
+    let view = serde_json::to_value(View::new(manager, &users[1])).unwrap();
+    assert_eq!(view["permissions"], 0);
+    assert!(view.get("email").is_none());
+
+    let pass = UserPass {
+        id: 7,
+        user_id: 1,
+        time_pass: Default::default(),
+        session_pass: "4".parse().unwrap(),
+        window: None,
+    };
+    let kim = Actor::from(&users[1]);
+    let view = serde_json::to_value(View::new(kim, &pass)).unwrap();
+    assert_eq!(view, json!({ "id": 7, "user_id": 1 }));
+    assert!(View::new(jo, &pass).allows(Action::Read));
+    assert!(!View::new(front_desk, &pass).allows(Action::Read));
+}